@@ -1,23 +1,154 @@
+use crate::date::Date;
+
 use std::cmp;
 use std::fmt;
+use std::str::FromStr;
+
+/// Languages of reports and QR-bills.
+pub const LANGUAGES: [&str; 4] = ["de", "en", "fr", "it"];
 
+/// Flatmate or other party, identified by a two letter acronym.
+///
+/// Optional attributes follow the name as `key=value`, with the value in
+/// double quotes if it contains spaces, or are given on indented lines
+/// after the account line:
+///
+/// ```text
+/// account AB Anna iban=CH9300762011623852957 address="Gasse 1, 8000 Zürich"
+///     email=anna@example.com
+///     active_from=01.04.2024
+/// ```
+///
+/// Known attributes are `iban`, `address`, `email`, `language` (de, en, fr
+/// or it) and the tenancy dates `active_from` and `active_until`. Invoices
+/// from `all` accounts are split by the days each account was active.
+/// Payments after `active_until` need the tag `#settle-up` in their note.
 pub struct Account {
     acronym: String,
     name: String,
+    iban: Option<String>,
+    address: Option<Address>,
+    email: Option<String>,
+    language: Option<String>,
+    active_from: Option<Date>,
+    active_until: Option<Date>,
 }
 impl Account {
-    pub fn new(captures: &regex::Captures) -> Self {
+    pub(crate) fn new(captures: &regex::Captures) -> Self {
         Account {
             acronym: captures.name("acronym").unwrap().as_str().to_owned(),
             name: captures.name("name").unwrap().as_str().to_owned(),
+            iban: None,
+            address: None,
+            email: None,
+            language: None,
+            active_from: None,
+            active_until: None,
         }
     }
+    pub(crate) fn set_attribute(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let is_set = match key {
+            "iban" => self.iban.is_some(),
+            "address" => self.address.is_some(),
+            "email" => self.email.is_some(),
+            "language" => self.language.is_some(),
+            "active_from" => self.active_from.is_some(),
+            "active_until" => self.active_until.is_some(),
+            _ => return Err(format!("unknown account attribute {}", key)),
+        };
+        if is_set {
+            return Err(format!("duplicate account attribute {}", key));
+        }
+        match key {
+            "iban" => self.iban = Some(parse_iban(value)?),
+            "address" => self.address = Some(value.parse()?),
+            "email" => self.email = Some(parse_email(value)?),
+            "language" => self.language = Some(parse_language(value)?),
+            "active_from" => self.active_from = Some(value.parse()?),
+            _ => self.active_until = Some(value.parse()?),
+        }
+        if let (Some(active_from), Some(active_until)) = (self.active_from, self.active_until) {
+            if active_until < active_from {
+                return Err(format!(
+                    "active_until {} before active_from {}",
+                    active_until, active_from
+                ));
+            }
+        }
+        Ok(())
+    }
     pub fn acronym(&self) -> &String {
         &self.acronym
     }
     pub fn name(&self) -> &String {
         &self.name
     }
+    /// IBAN without spaces.
+    pub fn iban(&self) -> Option<&String> {
+        self.iban.as_ref()
+    }
+    pub fn address(&self) -> Option<&Address> {
+        self.address.as_ref()
+    }
+    pub fn email(&self) -> Option<&String> {
+        self.email.as_ref()
+    }
+    /// One of [`LANGUAGES`].
+    pub fn language(&self) -> Option<&String> {
+        self.language.as_ref()
+    }
+    /// First day of the tenancy.
+    pub fn active_from(&self) -> Option<Date> {
+        self.active_from
+    }
+    /// Last day of the tenancy.
+    pub fn active_until(&self) -> Option<Date> {
+        self.active_until
+    }
+    /// Whether the date lies within the tenancy. Accounts without tenancy
+    /// dates are always active.
+    pub fn is_active(&self, date: Date) -> bool {
+        self.active_from
+            .is_none_or(|active_from| active_from <= date)
+            && self
+                .active_until
+                .is_none_or(|active_until| date <= active_until)
+    }
+    /// Number of days from `start` up to but excluding `end` within the
+    /// tenancy.
+    pub fn active_days(&self, start: Date, end: Date) -> u64 {
+        let first = self
+            .active_from
+            .map_or(start, |active_from| active_from.max(start));
+        let last_exclusive = self
+            .active_until
+            .and_then(|active_until| active_until.checked_add(1, 0, 0))
+            .map_or(end, |after_active_until| after_active_until.min(end));
+        first.days_until(last_exclusive).max(0) as u64
+    }
+    /// Attributes which are set, as written in the ledger.
+    pub fn attributes(&self) -> Vec<(&'static str, String)> {
+        let mut attributes: Vec<(&'static str, String)> = Vec::new();
+        if let Some(iban) = &self.iban {
+            attributes.push(("iban", iban.clone()));
+        }
+        if let Some(address) = &self.address {
+            attributes.push(("address", address.to_string()));
+        }
+        if let Some(email) = &self.email {
+            attributes.push(("email", email.clone()));
+        }
+        if let Some(language) = &self.language {
+            attributes.push(("language", language.clone()));
+        }
+        if let Some(active_from) = self.active_from {
+            attributes.push(("active_from", active_from.to_string()));
+        }
+        if let Some(active_until) = self.active_until {
+            attributes.push(("active_until", active_until.to_string()));
+        }
+        attributes
+    }
 }
 impl cmp::PartialEq for Account {
     fn eq(&self, other: &Self) -> bool {
@@ -26,13 +157,150 @@ impl cmp::PartialEq for Account {
 }
 impl fmt::Display for Account {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} -> {}", self.acronym, self.name)
+        write!(f, "{} -> {}", self.acronym, self.name)?;
+        for (key, value) in self.attributes() {
+            write!(f, " {}", format_attribute(key, &value))?;
+        }
+        Ok(())
+    }
+}
+
+/// Attribute as written in a ledger, `key="value"` if the value contains
+/// whitespace. Values containing double quotes stay unquoted, which only
+/// attribute lines after the account line accept.
+pub(crate) fn format_attribute(key: &str, value: &str) -> String {
+    if value.contains(char::is_whitespace) && !value.contains('"') {
+        format!("{}=\"{}\"", key, value)
+    } else {
+        format!("{}={}", key, value)
+    }
+}
+
+/// Postal address, written as `street, postal code town` with an optional
+/// two letter country code at the end, which defaults to CH.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Address {
+    street: String,
+    postal_code: String,
+    town: String,
+    country: String,
+}
+impl Address {
+    pub fn street(&self) -> &String {
+        &self.street
+    }
+    pub fn postal_code(&self) -> &String {
+        &self.postal_code
+    }
+    pub fn town(&self) -> &String {
+        &self.town
+    }
+    pub fn country(&self) -> &String {
+        &self.country
+    }
+}
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid address {}, expected \"street, postal code town[, country]\"",
+                string
+            )
+        };
+        let parts: Vec<&str> = string.split(',').map(str::trim).collect();
+        let (street, place, country) = match parts[..] {
+            [street, place] => (street, place, "CH"),
+            [street, place, country]
+                if country.len() == 2 && country.chars().all(|c| c.is_ascii_uppercase()) =>
+            {
+                (street, place, country)
+            }
+            _ => return Err(invalid()),
+        };
+        let (postal_code, town) = place.split_once(' ').ok_or_else(invalid)?;
+        if street.is_empty() || postal_code.is_empty() || town.trim().is_empty() {
+            return Err(invalid());
+        }
+        Ok(Address {
+            street: street.to_owned(),
+            postal_code: postal_code.to_owned(),
+            town: town.trim().to_owned(),
+            country: country.to_owned(),
+        })
+    }
+}
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {} {}", self.street, self.postal_code, self.town)?;
+        if self.country != "CH" {
+            write!(f, ", {}", self.country)?;
+        }
+        Ok(())
+    }
+}
+
+// Removes spaces and checks the country code, length and check digits
+fn parse_iban(string: &str) -> Result<String, String> {
+    let iban: String = string
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    // Only ASCII is accepted, so the byte slices below stay on characters
+    let well_formed = iban.chars().all(|c| c.is_ascii_alphanumeric())
+        && (15..=34).contains(&iban.len())
+        && iban[..2].chars().all(|c| c.is_ascii_uppercase())
+        && iban[2..4].chars().all(|c| c.is_ascii_digit());
+    if !well_formed {
+        return Err(format!("invalid IBAN {}", string));
+    }
+    // Move the first four characters to the end, replace letters by numbers
+    // from 10 to 35 and take the result modulo 97
+    let remainder = iban[4..]
+        .chars()
+        .chain(iban[..4].chars())
+        .fold(0u32, |remainder, c| {
+            let value = c.to_digit(36).unwrap();
+            if value < 10 {
+                (remainder * 10 + value) % 97
+            } else {
+                (remainder * 100 + value) % 97
+            }
+        });
+    if remainder != 1 {
+        return Err(format!("invalid IBAN {}: wrong check digits", string));
+    }
+    Ok(iban)
+}
+
+fn parse_email(string: &str) -> Result<String, String> {
+    match string.split_once('@') {
+        Some((local, domain))
+            if !local.is_empty() && domain.contains('.') && !domain.contains('@') =>
+        {
+            Ok(string.to_owned())
+        }
+        _ => Err(format!("invalid email address {}", string)),
+    }
+}
+
+fn parse_language(string: &str) -> Result<String, String> {
+    if LANGUAGES.contains(&string) {
+        Ok(string.to_owned())
+    } else {
+        Err(format!(
+            "unknown language {}, expected one of {}",
+            string,
+            LANGUAGES.join(", ")
+        ))
     }
 }
 
-pub fn find_index(acronym: &String, accounts: &Vec<Account>) -> usize {
+/// Position of the account with the given acronym.
+pub fn find_index(acronym: &str, accounts: &[Account]) -> Option<usize> {
     accounts
         .iter()
         .position(|account: &Account| account.acronym() == acronym)
-        .unwrap()
 }
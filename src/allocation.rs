@@ -0,0 +1,184 @@
+use crate::money::Money;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Decides which senders absorb the leftover cents of a split invoice.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum RemainderPolicy {
+    /// Leftover cents go to the senders in the order they are listed
+    #[default]
+    First,
+    /// Leftover cents go to the senders in reverse order
+    Last,
+    /// Leftover cents go to the recipient first if it is one of the senders
+    Recipient,
+}
+impl RemainderPolicy {
+    pub fn priority(&self, sender_indices: &[usize], recipient_index: usize) -> Vec<usize> {
+        let mut priority: Vec<usize> = (0..sender_indices.len()).collect();
+        match self {
+            RemainderPolicy::First => {}
+            RemainderPolicy::Last => priority.reverse(),
+            RemainderPolicy::Recipient => {
+                priority.sort_by_key(|&position| sender_indices[position] != recipient_index)
+            }
+        }
+        priority
+    }
+}
+impl FromStr for RemainderPolicy {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "first" => Ok(RemainderPolicy::First),
+            "last" => Ok(RemainderPolicy::Last),
+            "recipient" => Ok(RemainderPolicy::Recipient),
+            _ => Err(format!(
+                "unknown remainder policy {}, expected first, last or recipient",
+                string
+            )),
+        }
+    }
+}
+impl fmt::Display for RemainderPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = match self {
+            RemainderPolicy::First => "first",
+            RemainderPolicy::Last => "last",
+            RemainderPolicy::Recipient => "recipient",
+        };
+        write!(f, "{}", string)
+    }
+}
+
+// Largest weight in hundredths, so that the product with any amount fits
+const MAX_WEIGHT: u64 = 1_000_000 * 100;
+
+/// Parses a weight or percentage with up to two decimal places into
+/// hundredths. Weights above 1000000 are rejected.
+pub fn parse_weight(string: &str) -> Result<u64, String> {
+    let (units, fraction) = match string.split_once('.') {
+        Some((units, fraction)) => (units, fraction),
+        None => (string, ""),
+    };
+    if fraction.len() > 2 {
+        return Err(format!(
+            "invalid weight {}: more than two decimal places",
+            string
+        ));
+    }
+    let units = units
+        .parse::<u64>()
+        .ok()
+        .and_then(|units| units.checked_mul(100))
+        .ok_or_else(|| format!("invalid weight {}", string))?;
+    let fraction = if fraction.is_empty() {
+        0
+    } else {
+        format!("{:0<2}", fraction).parse::<u64>().unwrap()
+    };
+    let weight = units + fraction;
+    if weight > MAX_WEIGHT {
+        return Err(format!("invalid weight {}: too large", string));
+    }
+    Ok(weight)
+}
+
+/// Splits `total` proportionally to `weights` with the largest remainder
+/// method: every share gets the rounded down exact amount, the leftover cents
+/// go to the shares with the largest remainders. Ties are broken by the
+/// position of the share in `priority`.
+pub fn allocate(total: Money, weights: &[u64], priority: &[usize]) -> Vec<Money> {
+    assert_eq!(weights.len(), priority.len());
+    let weight_sum: i128 = weights.iter().map(|&weight| weight as i128).sum();
+    assert!(weight_sum > 0);
+    let total_cents = total.cents().unsigned_abs() as i128;
+
+    let mut shares: Vec<i128> = Vec::new();
+    let mut remainders: Vec<i128> = Vec::new();
+    for &weight in weights {
+        let exact = total_cents * weight as i128;
+        shares.push(exact / weight_sum);
+        remainders.push(exact % weight_sum);
+    }
+    let leftover = total_cents - shares.iter().sum::<i128>();
+
+    let mut order: Vec<usize> = priority.to_vec();
+    order.sort_by_key(|&position| -remainders[position]);
+    for &position in order.iter().take(leftover as usize) {
+        shares[position] += 1;
+    }
+
+    let sign = total.cents().signum();
+    shares
+        .into_iter()
+        .map(|share| Money::from_cents(sign * share as i64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(shares: &[Money]) -> Vec<i64> {
+        shares.iter().map(|share| share.cents()).collect()
+    }
+
+    #[test]
+    fn allocate_thirds_add_up_to_total() {
+        let shares = allocate(Money::from_cents(10000), &[100, 100, 100], &[0, 1, 2]);
+        assert_eq!(cents(&shares), vec![3334, 3333, 3333]);
+    }
+
+    #[test]
+    fn allocate_leftover_by_policy() {
+        let sender_indices = [3, 1, 2];
+        let priority = RemainderPolicy::First.priority(&sender_indices, 2);
+        let shares = allocate(Money::from_cents(200), &[100, 100, 100], &priority);
+        assert_eq!(cents(&shares), vec![67, 67, 66]);
+
+        let priority = RemainderPolicy::Last.priority(&sender_indices, 2);
+        let shares = allocate(Money::from_cents(200), &[100, 100, 100], &priority);
+        assert_eq!(cents(&shares), vec![66, 67, 67]);
+
+        let priority = RemainderPolicy::Recipient.priority(&sender_indices, 2);
+        assert_eq!(priority, vec![2, 0, 1]);
+        let shares = allocate(Money::from_cents(100), &[100, 100, 100], &priority);
+        assert_eq!(cents(&shares), vec![33, 33, 34]);
+        let shares = allocate(Money::from_cents(200), &[100, 100, 100], &priority);
+        assert_eq!(cents(&shares), vec![67, 66, 67]);
+    }
+
+    #[test]
+    fn allocate_largest_remainder_before_priority() {
+        // Exact shares 0.25, 0.75, the second one has the larger remainder
+        let shares = allocate(Money::from_cents(1), &[100, 300], &[0, 1]);
+        assert_eq!(cents(&shares), vec![0, 1]);
+    }
+
+    #[test]
+    fn allocate_negative_total() {
+        let shares = allocate(Money::from_cents(-10000), &[100, 100, 100], &[0, 1, 2]);
+        assert_eq!(cents(&shares), vec![-3334, -3333, -3333]);
+    }
+
+    #[test]
+    fn allocate_zero_weight_gets_nothing() {
+        let shares = allocate(Money::from_cents(1001), &[0, 100, 100], &[0, 1, 2]);
+        assert_eq!(cents(&shares), vec![0, 501, 500]);
+        let shares = allocate(Money::from_cents(1001), &[100, 0, 100], &[2, 1, 0]);
+        assert_eq!(cents(&shares), vec![500, 0, 501]);
+    }
+
+    #[test]
+    fn parse_weight_in_hundredths() {
+        assert_eq!(parse_weight("2"), Ok(200));
+        assert_eq!(parse_weight("1.5"), Ok(150));
+        assert_eq!(parse_weight("0.25"), Ok(25));
+        assert!(parse_weight("0.125").is_err());
+        assert_eq!(parse_weight("1000000"), Ok(MAX_WEIGHT));
+        assert!(parse_weight("1000000.01").is_err());
+    }
+}
@@ -1,18 +1,24 @@
 use crate::account::Account;
+use crate::date::Date;
+use crate::ledger::Ledger;
+use crate::money::Money;
+use crate::settlement::Transfer;
 use crate::transaction::Transaction;
 
 use std::ops;
 
+/// Open amount between two accounts. A negative balance is owed by the
+/// sender to the recipient, a positive one by the recipient to the sender.
 pub struct BalanceEntry {
     sender_index: usize,
     recipient_index: usize,
-    balance: f64,
+    balance: Money,
 }
 impl BalanceEntry {
     pub fn new(
         sender_index_param: usize,
         recipient_index_param: usize,
-        balance_param: f64,
+        balance_param: Money,
     ) -> Self {
         assert_ne!(sender_index_param, recipient_index_param);
         BalanceEntry {
@@ -27,21 +33,42 @@ impl BalanceEntry {
     pub fn recipient_index(&self) -> usize {
         self.recipient_index
     }
-    pub fn balance(&self) -> f64 {
+    pub fn balance(&self) -> Money {
         self.balance
     }
+    /// Transfer from the debtor to the creditor which settles this entry,
+    /// `None` if the balance is zero.
+    pub fn debt(&self) -> Option<Transfer> {
+        if self.balance > Money::ZERO {
+            Some(Transfer::new(
+                self.recipient_index,
+                self.sender_index,
+                self.balance,
+            ))
+        } else if self.balance.is_negative() {
+            Some(Transfer::new(
+                self.sender_index,
+                self.recipient_index,
+                self.balance.abs(),
+            ))
+        } else {
+            None
+        }
+    }
 }
-impl ops::AddAssign<f64> for BalanceEntry {
-    fn add_assign(&mut self, amount: f64) {
+impl ops::AddAssign<Money> for BalanceEntry {
+    fn add_assign(&mut self, amount: Money) {
         self.balance += amount;
     }
 }
-impl ops::SubAssign<f64> for BalanceEntry {
-    fn sub_assign(&mut self, amount: f64) {
+impl ops::SubAssign<Money> for BalanceEntry {
+    fn sub_assign(&mut self, amount: Money) {
         self.balance -= amount;
     }
 }
 
+/// Open amounts between pairs of accounts.
+#[derive(Default)]
 pub struct Balance {
     entries: Vec<BalanceEntry>,
 }
@@ -51,6 +78,28 @@ impl Balance {
             entries: Vec::new(),
         }
     }
+    /// Balance of the invoices and payments of the ledger up to its
+    /// accounting date.
+    pub fn from_ledger(ledger: &Ledger) -> Self {
+        Self::from_ledger_as_of(ledger, *ledger.accounting_date()).unwrap()
+    }
+    /// Balance of the invoices and payments of the ledger up to and
+    /// including `date`. Returns `None` if `date` is after the accounting
+    /// date, since recurring invoices without an end date only recur up to
+    /// it. Parse the ledger with [`Ledger::new_as_of`] for later dates.
+    pub fn from_ledger_as_of(ledger: &Ledger, date: Date) -> Option<Self> {
+        if date > *ledger.accounting_date() {
+            return None;
+        }
+        let mut balance = Balance::new();
+        for invoice in ledger.invoices_as_of(date) {
+            balance.add_invoice(invoice);
+        }
+        for payment in ledger.payments_as_of(date) {
+            balance.add_payment(payment);
+        }
+        Some(balance)
+    }
     pub fn add_invoice(&mut self, invoice: &Transaction) {
         self.add_transaction(
             invoice.sender_index(),
@@ -65,15 +114,15 @@ impl Balance {
             payment.amount(),
         );
     }
-    #[allow(dead_code)]
-    pub fn to_string(&self, accounts: &Vec<Account>) -> String {
+    pub fn to_string(&self, accounts: &[Account], currency: &str) -> String {
         let mut string = "Balance:\n".to_owned();
         for entry in &self.entries {
             string += format!(
-                "{} -> {} {} CHF\n",
+                "{} -> {} {} {}\n",
                 accounts[entry.sender_index].acronym(),
                 accounts[entry.recipient_index].acronym(),
-                entry.balance
+                entry.balance,
+                currency
             )
             .as_str();
         }
@@ -82,7 +131,7 @@ impl Balance {
     pub fn entries(&self) -> &Vec<BalanceEntry> {
         &self.entries
     }
-    fn add_transaction(&mut self, sender_index: usize, recipient_index: usize, amount: f64) {
+    fn add_transaction(&mut self, sender_index: usize, recipient_index: usize, amount: Money) {
         if let Some(entry) = self.entries.iter_mut().find(|entry| {
             (entry.sender_index == sender_index) && (entry.recipient_index == recipient_index)
         }) {
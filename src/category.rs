@@ -0,0 +1,106 @@
+use crate::date::Date;
+use crate::ledger::Ledger;
+use crate::money::Money;
+use crate::period::Period;
+use crate::transaction;
+
+use std::fmt::Write;
+
+/// Part of an invoice borne by one of its senders. Unlike the invoices of
+/// the ledger, shares include the part of a sender who is also the
+/// recipient, so they add up to what the household spent.
+pub struct Share {
+    account_index: usize,
+    amount: Money,
+    date: Date,
+    category: Option<String>,
+    tags: Vec<String>,
+}
+impl Share {
+    pub(crate) fn new(account_index: usize, amount: Money, date: Date, note: &str) -> Self {
+        Share {
+            account_index,
+            amount,
+            date,
+            category: transaction::parse_category(note),
+            tags: transaction::parse_tags(note),
+        }
+    }
+    pub fn account_index(&self) -> usize {
+        self.account_index
+    }
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+    pub fn date(&self) -> Date {
+        self.date
+    }
+    pub fn category(&self) -> Option<&String> {
+        self.category.as_ref()
+    }
+    pub fn tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own_tag| own_tag == tag)
+    }
+}
+
+/// Spending per category, largest first. Shares without a category are
+/// summed up under `None`.
+pub struct Breakdown {
+    categories: Vec<(Option<String>, Money)>,
+}
+impl Breakdown {
+    /// Breakdown of the shares of the account, or of the whole household
+    /// without an account, dated in the period. Returns `None` if the period
+    /// ends after the accounting date, like [`crate::Balance::from_ledger_as_of`].
+    pub fn new(ledger: &Ledger, account_index: Option<usize>, period: Period) -> Option<Self> {
+        if period.end() > *ledger.accounting_date() {
+            return None;
+        }
+        let mut categories: Vec<(Option<String>, Money)> = Vec::new();
+        for share in ledger.shares() {
+            if !period.contains(share.date())
+                || account_index.is_some_and(|index| index != share.account_index())
+            {
+                continue;
+            }
+            let category = share.category().cloned();
+            match categories.iter_mut().find(|(name, _)| *name == category) {
+                Some((_, amount)) => *amount += share.amount(),
+                None => categories.push((category, share.amount())),
+            }
+        }
+        categories.sort_by(|(name, amount), (other_name, other_amount)| {
+            other_amount
+                .cmp(amount)
+                .then_with(|| name.is_none().cmp(&other_name.is_none()))
+                .then_with(|| name.cmp(other_name))
+        });
+        Some(Breakdown { categories })
+    }
+    pub fn categories(&self) -> &[(Option<String>, Money)] {
+        &self.categories
+    }
+    pub fn total(&self) -> Money {
+        self.categories
+            .iter()
+            .fold(Money::ZERO, |total, (_, amount)| total + *amount)
+    }
+    pub fn to_string(&self, currency: &str) -> String {
+        let mut string = "Categories:\n".to_owned();
+        for (category, amount) in &self.categories {
+            writeln!(
+                string,
+                "{} {} {}",
+                category.as_deref().unwrap_or("(none)"),
+                amount,
+                currency
+            )
+            .unwrap();
+        }
+        writeln!(string, "Total {} {}", self.total(), currency).unwrap();
+        string
+    }
+}
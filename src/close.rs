@@ -0,0 +1,267 @@
+//! Year-end closing of a ledger.
+//!
+//! The closed ledger has the same header, exchange rates and accounts. The
+//! open balances at the cut-off date are carried over as
+//! `opening AB -> CD 12.50 31.12.2024` lines, meaning AB owes CD 12.50. The
+//! invoices, payments and openings dated after the cut-off are copied as
+//! written, and recurring invoices which are still running start with their
+//! first occurrence after the cut-off.
+
+use crate::account::{self, Account};
+use crate::balance::Balance;
+use crate::date::Date;
+use crate::ledger::Ledger;
+use crate::money::Money;
+
+use std::fmt::Write;
+
+/// Returns the text of the closed ledger. Fails if the cut-off is after the
+/// accounting date or if the closed ledger doesn't reproduce the balances
+/// at the cut-off and at the accounting date.
+pub fn close(ledger: &Ledger, cut_off: Date) -> Result<String, String> {
+    if cut_off > *ledger.accounting_date() {
+        return Err(format!(
+            "cut-off date {} is after the accounting date {}",
+            cut_off,
+            ledger.accounting_date()
+        ));
+    }
+    let accounts = ledger.accounts();
+    let mut text = String::new();
+    writeln!(text, "accounting_date {}", ledger.accounting_date()).unwrap();
+    writeln!(text, "remainder {}", ledger.remainder_policy()).unwrap();
+    writeln!(text, "currency {}", ledger.currency()).unwrap();
+    for rate in ledger.rates() {
+        writeln!(text, "rate {}", rate).unwrap();
+    }
+    writeln!(text).unwrap();
+    for account in accounts {
+        writeln!(text, "{}", account_lines(account)).unwrap();
+    }
+
+    writeln!(text, "\n// Open balances as of {}", cut_off).unwrap();
+    // The cut-off is not after the accounting date
+    let balance = Balance::from_ledger_as_of(ledger, cut_off).unwrap();
+    for debt in balance.entries().iter().filter_map(|entry| entry.debt()) {
+        writeln!(
+            text,
+            "opening {} -> {} {} {}",
+            accounts[debt.debtor_index()].acronym(),
+            accounts[debt.creditor_index()].acronym(),
+            debt.amount(),
+            cut_off
+        )
+        .unwrap();
+    }
+
+    let lines = ledger.lines_after(cut_off);
+    if !lines.is_empty() {
+        writeln!(text, "\n// Carried over from the closed ledger").unwrap();
+        for line in lines {
+            writeln!(text, "{}", line).unwrap();
+        }
+    }
+
+    verify(ledger, &text, cut_off)?;
+    Ok(text)
+}
+
+// Account line followed by one indented line per attribute
+fn account_lines(account: &Account) -> String {
+    let mut lines = format!("account {} {}", account.acronym(), account.name());
+    for (key, value) in account.attributes() {
+        write!(lines, "\n    {}", account::format_attribute(key, &value)).unwrap();
+    }
+    lines
+}
+
+fn verify(ledger: &Ledger, text: &str, cut_off: Date) -> Result<(), String> {
+    let closed_ledger: Ledger = text
+        .parse()
+        .map_err(|error| format!("closed ledger is invalid:\n{}", error))?;
+    let accounts = ledger.accounts();
+    let closed_accounts = closed_ledger.accounts();
+    for (index, account) in accounts.iter().enumerate() {
+        let closed_account = closed_accounts.get(index);
+        if closed_account.is_none_or(|closed_account| {
+            closed_account.acronym() != account.acronym()
+                || closed_account.name() != account.name()
+                || closed_account.attributes() != account.attributes()
+        }) {
+            return Err(format!(
+                "closed ledger doesn't reproduce the account {}",
+                account
+            ));
+        }
+    }
+    for date in [cut_off, *ledger.accounting_date()] {
+        let expected = owed_amounts(ledger, date);
+        let actual = owed_amounts(&closed_ledger, date);
+        let count = accounts.len();
+        for debtor_index in 0..count {
+            for creditor_index in 0..count {
+                let index = debtor_index * count + creditor_index;
+                if debtor_index < creditor_index && expected[index] != actual[index] {
+                    return Err(format!(
+                        "closed ledger has a balance of {} instead of {} from {} to {} on {}",
+                        actual[index],
+                        expected[index],
+                        accounts[debtor_index].acronym(),
+                        accounts[creditor_index].acronym(),
+                        date
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Amounts owed by one account to another as of the date, which is not after
+// the accounting date, indexed by debtor times the number of accounts plus
+// creditor
+fn owed_amounts(ledger: &Ledger, date: Date) -> Vec<Money> {
+    let count = ledger.accounts().len();
+    let mut amounts = vec![Money::ZERO; count * count];
+    for balance_entry in Balance::from_ledger_as_of(ledger, date).unwrap().entries() {
+        let sender_index = balance_entry.sender_index();
+        let recipient_index = balance_entry.recipient_index();
+        amounts[recipient_index * count + sender_index] += balance_entry.balance();
+        amounts[sender_index * count + recipient_index] -= balance_entry.balance();
+    }
+    amounts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEDGER: &str = "accounting_date 31.12.2024
+account AB Anna
+account CD Carl
+account EF Eva active_from=01.04.2024
+
+opening CD -> AB 12.50 31.12.2023
+invoice AB : CD -> CD 100.00 31.01.2024 - 31.12.2024 : monthly Rent
+invoice all -> EF 30.00 from 31.01.2024 monthly Internet
+invoice AB*2 : EF -> AB 45.55 29.02.2024 - 28.02.2025 : yearly Insurance
+payment AB -> CD 50.00 15.03.2024 Cash
+payment EF -> AB 20.00 15.08.2024 Cash
+";
+
+    #[test]
+    fn close_text() {
+        let ledger: Ledger = LEDGER.parse().unwrap();
+        let text = close(&ledger, "31.03.2024".parse().unwrap()).unwrap();
+        assert_eq!(
+            text,
+            "accounting_date 31.12.2024
+remainder first
+currency CHF
+
+account AB Anna
+account CD Carl
+account EF Eva
+    active_from=01.04.2024
+
+// Open balances as of 31.03.2024
+opening AB -> CD 87.50 31.03.2024
+opening AB -> EF 24.93 31.03.2024
+opening CD -> EF 40.11 31.03.2024
+
+// Carried over from the closed ledger
+invoice AB : CD -> CD 100.00 30.04.2024 Rent
+invoice AB : CD -> CD 100.00 31.05.2024 - 31.12.2024 : monthly Rent
+invoice AB*31 : CD*31 : EF*31 -> EF 30.00 30.04.2024 Internet
+invoice all -> EF 30.00 from 31.05.2024 monthly Internet
+invoice AB*2 : EF -> AB 45.55 28.02.2025 Insurance
+payment EF -> AB 20.00 15.08.2024 Cash
+"
+        );
+    }
+
+    // Closes the ledger at every month end and compares the balances
+    fn assert_closed_ledgers_reproduce_balances(text: &str) {
+        let ledger: Ledger = text.parse().unwrap();
+        let accounting_date = *ledger.accounting_date();
+        for month in 1..=12 {
+            let cut_off = Date::from_ymd(2024, month, 1)
+                .unwrap()
+                .checked_add(0, 1, 0)
+                .and_then(|date| date.previous_day())
+                .unwrap();
+            let text = close(&ledger, cut_off).unwrap();
+            let closed_ledger: Ledger = text.parse().unwrap();
+            assert_eq!(*closed_ledger.accounting_date(), accounting_date);
+            for date in [cut_off, accounting_date] {
+                assert_eq!(
+                    owed_amounts(&closed_ledger, date),
+                    owed_amounts(&ledger, date),
+                    "closed on {}, balances on {}",
+                    cut_off,
+                    date
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn closed_ledger_reproduces_balances() {
+        assert_closed_ledgers_reproduce_balances(LEDGER);
+    }
+
+    #[test]
+    fn closed_ledger_reproduces_tenancy_in_clamped_month() {
+        // The occurrence on 30.04 covers the days up to 31.05, during which
+        // EF moves in
+        let text = "accounting_date 31.12.2024
+account AB Anna
+account CD Carl
+account EF Eva active_from=15.05.2024
+
+invoice all -> AB 31.00 from 31.01.2024 monthly Internet
+";
+        assert_closed_ledgers_reproduce_balances(text);
+        let ledger: Ledger = text.parse().unwrap();
+        let closed_text = close(&ledger, "31.03.2024".parse().unwrap()).unwrap();
+        assert!(closed_text.ends_with(
+            "invoice AB*31 : CD*31 : EF*16 -> AB 31.00 30.04.2024 Internet
+invoice all -> AB 31.00 from 31.05.2024 monthly Internet
+"
+        ));
+    }
+
+    #[test]
+    fn close_keeps_attributes() {
+        let text = "accounting_date 31.12.2024
+account AB Anna email=anna@example.com
+    address=Gasse \"Alt\" 1, 8000 Zurich
+account CD Carl address=\"Weg 2, 8001 Zurich\"
+";
+        let ledger: Ledger = text.parse().unwrap();
+        let closed_text = close(&ledger, "30.06.2024".parse().unwrap()).unwrap();
+        assert!(closed_text.contains(
+            "account AB Anna
+    address=Gasse \"Alt\" 1, 8000 Zurich
+    email=anna@example.com
+account CD Carl
+    address=\"Weg 2, 8001 Zurich\"
+"
+        ));
+        let closed_ledger: Ledger = closed_text.parse().unwrap();
+        let address = closed_ledger.accounts()[0].address().unwrap();
+        assert_eq!(address.street(), "Gasse \"Alt\" 1");
+
+        // On the account line the quotes end the value early
+        let wrong_text =
+            closed_text.replace("account AB Anna\n    address=", "account AB Anna address=");
+        let error = verify(&ledger, &wrong_text, "30.06.2024".parse().unwrap()).unwrap_err();
+        assert!(error.starts_with("closed ledger doesn't reproduce the account AB"));
+    }
+
+    #[test]
+    fn close_after_accounting_date() {
+        let ledger: Ledger = LEDGER.parse().unwrap();
+        assert!(close(&ledger, "01.01.2025".parse().unwrap()).is_err());
+    }
+}
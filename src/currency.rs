@@ -0,0 +1,204 @@
+use crate::date::Date;
+use crate::money::Money;
+
+use std::fmt;
+
+// Exchange rates are stored in millionths
+const RATE_SCALE: i128 = 1_000_000;
+
+/// Currency of a ledger if none is given with the `currency` directive.
+pub const DEFAULT_CURRENCY: &str = "CHF";
+
+/// Amount in a currency other than the ledger currency, as written in the
+/// ledger before conversion.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ForeignAmount {
+    amount: Money,
+    currency: String,
+}
+impl ForeignAmount {
+    pub fn new(amount: Money, currency: String) -> Self {
+        ForeignAmount { amount, currency }
+    }
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+    pub fn currency(&self) -> &String {
+        &self.currency
+    }
+}
+impl fmt::Display for ForeignAmount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.amount, self.currency)
+    }
+}
+
+/// Rate from one currency to another, in effect from its date until the
+/// next rate between the same currencies.
+#[derive(Clone, Debug)]
+pub struct ExchangeRate {
+    from: String,
+    to: String,
+    millionths: i64,
+    date: Date,
+}
+impl ExchangeRate {
+    /// Parses `rate` with up to six decimal places, e.g. 0.95 for 1 EUR = 0.95 CHF.
+    pub fn new(from: String, to: String, rate: &str, date: Date) -> Result<Self, String> {
+        let invalid = || format!("invalid exchange rate {}", rate);
+        let (units, fraction) = match rate.split_once('.') {
+            Some((units, fraction)) => (units, fraction),
+            None => (rate, ""),
+        };
+        if fraction.len() > 6 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!(
+                "invalid exchange rate {}: more than six decimal places",
+                rate
+            ));
+        }
+        let mut millionths = units
+            .parse::<i64>()
+            .ok()
+            .and_then(|units| units.checked_mul(RATE_SCALE as i64))
+            .ok_or_else(invalid)?;
+        if !fraction.is_empty() {
+            millionths = millionths
+                .checked_add(format!("{:0<6}", fraction).parse::<i64>().unwrap())
+                .ok_or_else(invalid)?;
+        }
+        if millionths <= 0 {
+            return Err(format!(
+                "invalid exchange rate {}: must be above zero",
+                rate
+            ));
+        }
+        Ok(ExchangeRate {
+            from,
+            to,
+            millionths,
+            date,
+        })
+    }
+    pub fn from(&self) -> &String {
+        &self.from
+    }
+    pub fn to(&self) -> &String {
+        &self.to
+    }
+    pub fn date(&self) -> Date {
+        self.date
+    }
+    /// Rate as a decimal number without trailing zeros, e.g. 0.95.
+    pub fn rate(&self) -> String {
+        let units = self.millionths / RATE_SCALE as i64;
+        let fraction = format!("{:06}", self.millionths % RATE_SCALE as i64);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            units.to_string()
+        } else {
+            format!("{}.{}", units, fraction)
+        }
+    }
+    fn multiply(&self, amount: Money) -> Option<Money> {
+        divide_rounded(amount.cents() as i128 * self.millionths as i128, RATE_SCALE)
+    }
+    fn divide(&self, amount: Money) -> Option<Money> {
+        divide_rounded(amount.cents() as i128 * RATE_SCALE, self.millionths as i128)
+    }
+}
+impl fmt::Display for ExchangeRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.from, self.to, self.rate(), self.date)
+    }
+}
+
+// Rounds half away from zero
+fn divide_rounded(dividend: i128, divisor: i128) -> Option<Money> {
+    let quotient = (dividend.abs() + divisor / 2) / divisor;
+    let cents = i64::try_from(quotient).ok()?;
+    Some(Money::from_cents(if dividend < 0 { -cents } else { cents }))
+}
+
+/// Converts `amount` from currency `from` to currency `to` with the latest
+/// rate dated on or before `date`. A rate given in the opposite direction is
+/// used inverted. Fails if there is no such rate or if the converted amount
+/// is too large.
+pub fn convert(
+    amount: Money,
+    from: &str,
+    to: &str,
+    date: Date,
+    rates: &[ExchangeRate],
+) -> Result<Money, String> {
+    if from == to {
+        return Ok(amount);
+    }
+    let rate = rates
+        .iter()
+        .filter(|rate| {
+            rate.date <= date
+                && ((rate.from == from && rate.to == to) || (rate.from == to && rate.to == from))
+        })
+        .max_by_key(|rate| rate.date)
+        .ok_or_else(|| format!("no exchange rate from {} to {} on {}", from, to, date))?;
+    let converted = if rate.from == from {
+        rate.multiply(amount)
+    } else {
+        rate.divide(amount)
+    };
+    converted
+        .filter(|converted| converted.abs() <= Money::MAX)
+        .ok_or_else(|| format!("amount {} {} is too large in {}", amount, from, to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(string: &str) -> Date {
+        string.parse().unwrap()
+    }
+
+    fn rate(rate: &str) -> Result<ExchangeRate, String> {
+        ExchangeRate::new("EUR".to_owned(), "CHF".to_owned(), rate, date("01.01.2024"))
+    }
+
+    #[test]
+    fn rate_out_of_range() {
+        assert_eq!(rate("0.95").unwrap().rate(), "0.95");
+        assert!(rate("9223372036854.775807").is_ok());
+        assert_eq!(
+            rate("9223372036854.999999").unwrap_err(),
+            "invalid exchange rate 9223372036854.999999"
+        );
+        assert!(rate("0").is_err());
+    }
+
+    #[test]
+    fn convert_both_directions() {
+        let rates = [rate("0.95").unwrap()];
+        let amount = Money::from_cents(1000);
+        let date = date("01.02.2024");
+        assert_eq!(
+            convert(amount, "EUR", "CHF", date, &rates),
+            Ok(Money::from_cents(950))
+        );
+        assert_eq!(
+            convert(amount, "CHF", "EUR", date, &rates),
+            Ok(Money::from_cents(1053))
+        );
+        assert_eq!(
+            convert(amount, "EUR", "CHF", "31.12.2023".parse().unwrap(), &rates),
+            Err("no exchange rate from EUR to CHF on 31.12.2023".to_owned())
+        );
+    }
+
+    #[test]
+    fn convert_too_large() {
+        let rates = [rate("9000000").unwrap()];
+        assert_eq!(
+            convert(Money::MAX, "EUR", "CHF", date("01.02.2024"), &rates),
+            Err("amount 1000000000000.00 EUR is too large in CHF".to_owned())
+        );
+    }
+}
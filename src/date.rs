@@ -1,29 +1,88 @@
 use chrono::{Datelike, Duration, NaiveDate};
+use regex::Regex;
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Calendar date, written as dd.mm.yyyy.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Date {
     naive_date: NaiveDate,
 }
 impl Date {
-    pub fn new(captures: &regex::Captures) -> Self {
-        Date {
-            naive_date: NaiveDate::from_ymd(
-                captures.name("year").unwrap().as_str().parse().unwrap(),
-                captures.name("month").unwrap().as_str().parse().unwrap(),
-                captures.name("day").unwrap().as_str().parse().unwrap(),
-            ),
-        }
-    }
-    pub fn add(&mut self, days: u32, months: u32, years: u32) {
-        let month0_sum = self.naive_date.month0() + months;
-        let mut new_naive_date = NaiveDate::from_ymd(
-            self.naive_date.year() + (years + (month0_sum / 12)) as i32,
-            (month0_sum % 12) + 1,
-            self.naive_date.day(),
-        );
-        new_naive_date += Duration::days(days as i64);
-        self.naive_date = new_naive_date;
+    pub(crate) fn new(captures: &regex::Captures) -> Option<Self> {
+        Some(Date {
+            naive_date: NaiveDate::from_ymd_opt(
+                captures.name("year").unwrap().as_str().parse().ok()?,
+                captures.name("month").unwrap().as_str().parse().ok()?,
+                captures.name("day").unwrap().as_str().parse().ok()?,
+            )?,
+        })
+    }
+    pub(crate) fn from_ymd(year: i32, month: u32, day: u32) -> Option<Self> {
+        Some(Date {
+            naive_date: NaiveDate::from_ymd_opt(year, month, day)?,
+        })
+    }
+    pub fn year(&self) -> i32 {
+        self.naive_date.year()
+    }
+    pub fn month(&self) -> u32 {
+        self.naive_date.month()
+    }
+    /// The day before, `None` if it is out of range.
+    pub fn previous_day(&self) -> Option<Self> {
+        Some(Date {
+            naive_date: self.naive_date.pred_opt()?,
+        })
+    }
+    /// Date in ISO 8601 format, yyyy-mm-dd.
+    pub fn to_iso_string(&self) -> String {
+        self.naive_date.format("%Y-%m-%d").to_string()
+    }
+    /// Number of days from this date to `other`, negative if `other` is
+    /// earlier.
+    pub fn days_until(&self, other: Date) -> i64 {
+        other
+            .naive_date
+            .signed_duration_since(self.naive_date)
+            .num_days()
+    }
+    /// Adds the given period. Months and years are added first; if the day
+    /// does not exist in the resulting month, it is clamped to the last day
+    /// of that month (31.01 + 1 month = 29.02). Returns `None` if the result
+    /// is out of range.
+    pub fn checked_add(&self, days: u32, months: u32, years: u32) -> Option<Self> {
+        let month0_sum = self.naive_date.month0().checked_add(months)?;
+        let year = self
+            .naive_date
+            .year()
+            .checked_add(i32::try_from(years.checked_add(month0_sum / 12)?).ok()?)?;
+        let month = (month0_sum % 12) + 1;
+        let day = self.naive_date.day().min(days_in_month(year, month)?);
+        let naive_date = NaiveDate::from_ymd_opt(year, month, day)?
+            .checked_add_signed(Duration::days(days as i64))?;
+        Some(Date { naive_date })
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> Option<u32> {
+    let first_of_next_month = if month == 12 {
+        NaiveDate::from_ymd_opt(year.checked_add(1)?, 1, 1)?
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)?
+    };
+    Some(first_of_next_month.pred_opt()?.day())
+}
+impl FromStr for Date {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let date_regex =
+            Regex::new(r"^(?P<day>\d{1,2})\.(?P<month>\d{1,2})\.(?P<year>\d{4})$").unwrap();
+        date_regex
+            .captures(string)
+            .and_then(|captures| Date::new(&captures))
+            .ok_or_else(|| format!("invalid date {}, expected dd.mm.yyyy", string))
     }
 }
 impl fmt::Display for Date {
@@ -37,3 +96,62 @@ impl fmt::Display for Date {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(string: &str) -> Date {
+        string.parse().unwrap()
+    }
+
+    #[test]
+    fn checked_add_clamps_to_month_end() {
+        assert_eq!(
+            date("31.01.2024").checked_add(0, 1, 0),
+            Some(date("29.02.2024"))
+        );
+        assert_eq!(
+            date("31.01.2023").checked_add(0, 1, 0),
+            Some(date("28.02.2023"))
+        );
+        assert_eq!(
+            date("31.01.2024").checked_add(0, 2, 0),
+            Some(date("31.03.2024"))
+        );
+        assert_eq!(
+            date("31.03.2024").checked_add(0, 1, 0),
+            Some(date("30.04.2024"))
+        );
+        assert_eq!(
+            date("29.02.2024").checked_add(0, 0, 1),
+            Some(date("28.02.2025"))
+        );
+        assert_eq!(
+            date("29.02.2024").checked_add(0, 0, 4),
+            Some(date("29.02.2028"))
+        );
+    }
+
+    #[test]
+    fn checked_add_days_after_months() {
+        assert_eq!(
+            date("31.01.2024").checked_add(1, 1, 0),
+            Some(date("01.03.2024"))
+        );
+        assert_eq!(
+            date("15.11.2024").checked_add(0, 2, 0),
+            Some(date("15.01.2025"))
+        );
+        assert_eq!(
+            date("31.12.2024").checked_add(1, 0, 0),
+            Some(date("01.01.2025"))
+        );
+    }
+
+    #[test]
+    fn checked_add_out_of_range() {
+        assert_eq!(date("01.01.2024").checked_add(0, u32::MAX, 0), None);
+        assert_eq!(date("01.01.2024").checked_add(0, 0, u32::MAX), None);
+    }
+}
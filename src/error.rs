@@ -0,0 +1,106 @@
+use std::error;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A problem found on one line of a ledger.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    line: usize,
+    columns: Range<usize>,
+    reason: String,
+}
+impl ParseError {
+    pub(crate) fn new(line_number: usize, line: &str, bytes: Range<usize>, reason: String) -> Self {
+        // Convert byte offsets to 1-based character columns
+        let start = line[..bytes.start].chars().count() + 1;
+        let end = line[..bytes.end].chars().count() + 1;
+        ParseError {
+            line: line_number,
+            columns: start..end,
+            reason,
+        }
+    }
+    pub(crate) fn whole_line(line_number: usize, line: &str, reason: String) -> Self {
+        Self::new(line_number, line, 0..line.len(), reason)
+    }
+    pub(crate) fn file(reason: String) -> Self {
+        ParseError {
+            line: 0,
+            columns: 0..0,
+            reason,
+        }
+    }
+    /// Line number starting at 1, or 0 if the problem concerns the whole file.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    /// Character columns starting at 1, the end is exclusive.
+    pub fn columns(&self) -> &Range<usize> {
+        &self.columns
+    }
+    pub fn reason(&self) -> &String {
+        &self.reason
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.reason)
+        } else if self.columns.is_empty() {
+            write!(f, "{}: {}", self.line, self.reason)
+        } else {
+            write!(
+                f,
+                "{}:{}-{}: {}",
+                self.line,
+                self.columns.start,
+                self.columns.end - 1,
+                self.reason
+            )
+        }
+    }
+}
+
+/// All problems found while parsing a ledger.
+#[derive(Debug)]
+pub struct LedgerError {
+    path: PathBuf,
+    errors: Vec<ParseError>,
+}
+impl LedgerError {
+    pub(crate) fn new(path: &Path, errors: Vec<ParseError>) -> Self {
+        LedgerError {
+            path: path.to_path_buf(),
+            errors,
+        }
+    }
+    /// Replaces the path shown in front of each problem, e.g. to name the
+    /// source of a ledger parsed from a reader.
+    pub fn with_path(mut self, path: &Path) -> Self {
+        self.path = path.to_path_buf();
+        self
+    }
+    /// Path of the ledger file, empty if the ledger was not read from a file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn errors(&self) -> &Vec<ParseError> {
+        &self.errors
+    }
+}
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for error in &self.errors {
+            if self.path.as_os_str().is_empty() {
+                writeln!(f, "{}", error)?;
+            } else if error.line == 0 {
+                writeln!(f, "{}: {}", self.path.display(), error)?;
+            } else {
+                writeln!(f, "{}:{}", self.path.display(), error)?;
+            }
+        }
+        Ok(())
+    }
+}
+impl error::Error for LedgerError {}
@@ -0,0 +1,125 @@
+use crate::account::Account;
+use crate::ledger::Ledger;
+use crate::money::Money;
+use crate::transaction::Transaction;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    Invoice,
+    Payment,
+}
+
+/// Invoice or payment together with the net balance of an account after it.
+/// A positive balance is owed to the account, a negative one by the account.
+pub struct HistoryEntry<'a> {
+    transaction: &'a Transaction,
+    kind: EntryKind,
+    change: Money,
+    balance: Money,
+}
+impl<'a> HistoryEntry<'a> {
+    pub fn transaction(&self) -> &'a Transaction {
+        self.transaction
+    }
+    pub fn kind(&self) -> EntryKind {
+        self.kind
+    }
+    /// Change of the net balance by this entry.
+    pub fn change(&self) -> Money {
+        self.change
+    }
+    /// Net balance after this entry.
+    pub fn balance(&self) -> Money {
+        self.balance
+    }
+    pub fn to_string(&self, accounts: &[Account], currency: &str) -> String {
+        let kind = match self.kind {
+            EntryKind::Invoice => "invoice",
+            EntryKind::Payment => "payment",
+        };
+        let sign = if self.change.is_negative() { "" } else { "+" };
+        format!(
+            "{} {}\t{}{} {}\t{} {}",
+            kind,
+            self.transaction.to_string(accounts, currency),
+            sign,
+            self.change,
+            currency,
+            self.balance,
+            currency
+        )
+    }
+}
+
+/// Running net balance of the account over all its invoices and payments up
+/// to the accounting date, sorted by date. Invoices come before payments of
+/// the same date.
+pub fn account_history(ledger: &Ledger, account_index: usize) -> Vec<HistoryEntry<'_>> {
+    history(ledger, |transaction| {
+        transaction.sender_index() == account_index
+            || transaction.recipient_index() == account_index
+    })
+    .map(|(transaction, kind)| (transaction, kind, change(transaction, kind, account_index)))
+    .scan(Money::ZERO, running_balance)
+    .collect()
+}
+
+/// Running balance between two accounts from the view of the account with
+/// `account_index`. The last balance equals the one of [`crate::Balance`]
+/// with the account as sender.
+pub fn pair_history(
+    ledger: &Ledger,
+    account_index: usize,
+    other_index: usize,
+) -> Vec<HistoryEntry<'_>> {
+    history(ledger, |transaction| {
+        (transaction.sender_index() == account_index
+            && transaction.recipient_index() == other_index)
+            || (transaction.sender_index() == other_index
+                && transaction.recipient_index() == account_index)
+    })
+    .map(|(transaction, kind)| (transaction, kind, change(transaction, kind, account_index)))
+    .scan(Money::ZERO, running_balance)
+    .collect()
+}
+
+fn history(
+    ledger: &Ledger,
+    filter: impl Fn(&Transaction) -> bool,
+) -> impl Iterator<Item = (&Transaction, EntryKind)> {
+    let accounting_date = *ledger.accounting_date();
+    let mut entries: Vec<(&Transaction, EntryKind)> = ledger
+        .invoices_as_of(accounting_date)
+        .map(|invoice| (invoice, EntryKind::Invoice))
+        .chain(
+            ledger
+                .payments_as_of(accounting_date)
+                .map(|payment| (payment, EntryKind::Payment)),
+        )
+        .filter(|(transaction, _)| filter(transaction))
+        .collect();
+    // The sort is stable, so invoices stay before payments of the same date
+    entries.sort_by_key(|(transaction, _)| transaction.date());
+    entries.into_iter()
+}
+
+fn change(transaction: &Transaction, kind: EntryKind, account_index: usize) -> Money {
+    let amount = transaction.amount();
+    match (kind, transaction.sender_index() == account_index) {
+        (EntryKind::Invoice, true) | (EntryKind::Payment, false) => -amount,
+        (EntryKind::Invoice, false) | (EntryKind::Payment, true) => amount,
+    }
+}
+
+fn running_balance<'a>(
+    balance: &mut Money,
+    (transaction, kind, change): (&'a Transaction, EntryKind, Money),
+) -> Option<HistoryEntry<'a>> {
+    *balance += change;
+    Some(HistoryEntry {
+        transaction,
+        kind,
+        change,
+        balance: *balance,
+    })
+}
@@ -1,286 +1,1480 @@
 use crate::account::{self, Account};
+use crate::allocation::{self, RemainderPolicy};
+use crate::category::Share;
+use crate::currency::{self, ExchangeRate, ForeignAmount};
 use crate::date::Date;
-use crate::transaction::Transaction;
+use crate::error::{LedgerError, ParseError};
+use crate::money::Money;
+use crate::recurrence::Recurrence;
+use crate::transaction::{self, Transaction};
 
-use regex::Regex;
+use regex::{Match, Regex};
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::mem;
+use std::ops::Range;
 use std::path::Path;
+use std::str::FromStr;
 
 pub struct Ledger {
     accounting_date: Date,
+    remainder_policy: RemainderPolicy,
+    currency: String,
+    rates: Vec<ExchangeRate>,
     accounts: Vec<Account>,
     invoices: Vec<Transaction>,
     payments: Vec<Transaction>,
+    shares: Vec<Share>,
+    entry_lines: Vec<EntryLine>,
+    warnings: Vec<ParseError>,
 }
 impl Ledger {
-    pub fn new(input: &Path) -> Self {
-        // Regex pattern strings
-        let comment_pattern = r"^//";
-        let date_pattern = r"(?P<day>\d{1,2})\.(?P<month>\d{1,2})\.(?P<year>\d{4})";
-        let time_range_pattern = r"(?P<end_date>\s-\s\d{1,2}\.\d{1,2}\.\d{4})(?P<frequency>\s:\s\d{1,2}\.\d{1,2}\.\d{4})";
-        let amount_pattern = r"(?P<amount>\d+\.\d+)";
-        let note_pattern = r"(?P<note>.+)";
-        let header_pattern = r"^accounting_date\s".to_owned() + &date_pattern.to_owned() + r"$";
-        let account_pattern = r"^account\s(?P<acronym>[A-Z]{2})\s(?P<name>.+)";
-        let invoice_pattern =
-            r"^invoice\s(?P<first_sender>[A-Z]{2})(\s:\s[A-Z]{2})*(\s->\s[A-Z]{2})+\s".to_owned()
-                + &amount_pattern.to_owned()
-                + r"\s(?P<start_date>\d{1,2}\.\d{1,2}\.\d{4})(?P<time_range>\s-\s\d{1,2}\.\d{1,2}\.\d{4}\s:\s\d{1,2}\.\d{1,2}\.\d{4})?\s"
-                + &note_pattern.to_owned();
-        let additional_sender_pattern = r"\s:\s(?P<additional_sender>[A-Z]{2})";
-        let recipient_pattern = r"\s\->\s(?P<recipient>[A-Z]{2})";
-        let payment_pattern = r"^payment\s(?P<sender>[A-Z]{2})\s->\s(?P<recipient>[A-Z]{2})\s"
-            .to_owned()
-            + &amount_pattern.to_owned()
-            + r"\s"
-            + &date_pattern.to_owned()
-            + r"\s"
-            + &note_pattern.to_owned();
-
-        // Regex objects
-        let comment_regex = Regex::new(comment_pattern).unwrap();
-        let date_regex = Regex::new(date_pattern).unwrap();
-        let time_range_regex = Regex::new(time_range_pattern).unwrap();
-        let header_regex = Regex::new(header_pattern.as_str()).unwrap();
-        let account_regex = Regex::new(account_pattern).unwrap();
-        let invoice_regex = Regex::new(invoice_pattern.as_str()).unwrap();
-        let additional_sender_regex = Regex::new(additional_sender_pattern).unwrap();
-        let recipient_regex = Regex::new(recipient_pattern).unwrap();
-        let payment_regex = Regex::new(payment_pattern.as_str()).unwrap();
-
-        // Open input file
-        let transactions_file = File::open(input).unwrap();
-        let mut transactions_lines = BufReader::new(transactions_file).lines();
-
-        // Parse header
-        let first_line = transactions_lines.next().unwrap().unwrap();
-        let header_captures = header_regex.captures(&first_line).unwrap();
-        let accounting_date = Date::new(&header_captures);
-
-        // Parse accounts, invoices and transactions
-        let mut accounts: Vec<Account> = Vec::new();
-        let mut invoices: Vec<Transaction> = Vec::new();
-        let mut payments: Vec<Transaction> = Vec::new();
-
-        let mut add_invoice =
-            |sender_index: usize, recipient_index: usize, amount: f64, date: Date, note: String| {
-                if sender_index != recipient_index {
-                    let mut actual_amount = amount;
-                    let existing_invoice = invoices.iter().find(|invoice| {
-                        (((invoice.sender_index() == sender_index)
-                            && (invoice.recipient_index() == recipient_index))
-                            || ((invoice.sender_index() == recipient_index)
-                                && (invoice.recipient_index() == sender_index)))
-                            && (invoice.date() == date)
-                            && (invoice.note() == note)
-                    });
-                    if existing_invoice.is_some() {
-                        // This invoice exists already
-                        let existing_invoice_clone = (*existing_invoice.unwrap()).clone();
-                        // Add the amount of the existing invoice to the new invoice
-                        actual_amount += if existing_invoice_clone.sender_index() == sender_index {
-                            existing_invoice_clone.amount()
-                        } else {
-                            -existing_invoice_clone.amount()
-                        };
-                        // Remove existing invoice
-                        invoices.retain(|invoice| invoice != &existing_invoice_clone);
-                    }
-                    invoices.push(Transaction::new(
-                        sender_index,
-                        recipient_index,
-                        actual_amount,
-                        date,
-                        note,
-                    ));
-                }
+    /// Parses the ledger file at `input`.
+    pub fn new(input: &Path) -> Result<Self, LedgerError> {
+        Self::from_reader(Self::open(input)?).map_err(|error| error.with_path(input))
+    }
+    /// Parses the ledger file at `input`, evaluating it at `as_of` instead of
+    /// the accounting date given in its header. `as_of` becomes the
+    /// accounting date of the ledger.
+    pub fn new_as_of(input: &Path, as_of: Date) -> Result<Self, LedgerError> {
+        Self::from_reader_as_of(Self::open(input)?, as_of).map_err(|error| error.with_path(input))
+    }
+    /// Parses a ledger from any buffered reader, e.g. stdin or an embedded
+    /// string. Errors carry no file path.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, LedgerError> {
+        Self::parse(reader, None).map_err(|errors| LedgerError::new(Path::new(""), errors))
+    }
+    /// Parses a ledger from any buffered reader, evaluating it at `as_of`
+    /// instead of the accounting date given in its header.
+    pub fn from_reader_as_of<R: BufRead>(reader: R, as_of: Date) -> Result<Self, LedgerError> {
+        Self::parse(reader, Some(as_of)).map_err(|errors| LedgerError::new(Path::new(""), errors))
+    }
+    fn open(input: &Path) -> Result<BufReader<File>, LedgerError> {
+        let transactions_file = File::open(input).map_err(|error| {
+            LedgerError::new(
+                input,
+                vec![ParseError::file(format!("cannot open file: {}", error))],
+            )
+        })?;
+        Ok(BufReader::new(transactions_file))
+    }
+    fn parse<R: BufRead>(reader: R, as_of: Option<Date>) -> Result<Self, Vec<ParseError>> {
+        let mut parser = Parser::new(as_of);
+        for (index, wraped_line) in reader.lines().enumerate() {
+            let line_number = index + 1;
+            let result = match wraped_line {
+                Ok(line) => parser.parse_line(line_number, &line),
+                Err(error) => Err(ParseError::new(
+                    line_number,
+                    "",
+                    0..0,
+                    format!("cannot read line: {}", error),
+                )),
             };
-
-        for wraped_line in transactions_lines {
-            let line = wraped_line.unwrap();
-            if line == "" {
-                // Ignore empty line
-            } else if let Some(_captures) = comment_regex.captures(&line) {
-                // Ignore comment
-            } else if let Some(captures) = account_regex.captures(&line) {
-                // Add new account
-                accounts.push(Account::new(&captures));
-            } else if let Some(captures) = invoice_regex.captures(&line) {
-                // Determine invoice dates
-                let start_date = Date::new(
-                    &date_regex
-                        .captures(captures.name("start_date").unwrap().as_str())
-                        .unwrap(),
-                );
-                if start_date > accounting_date {
-                    // Ignore invoice
-                    continue;
-                }
-                let mut invoice_dates: Vec<Date> = Vec::new();
-                invoice_dates.push(start_date);
-                if let Some(time_range_match) = captures.name("time_range") {
-                    let time_range_captures = time_range_regex
-                        .captures(time_range_match.as_str())
-                        .unwrap();
-                    let mut end_date = Date::new(
-                        &date_regex
-                            .captures(time_range_captures.name("end_date").unwrap().as_str())
-                            .unwrap(),
-                    );
-                    if end_date > accounting_date {
-                        end_date = accounting_date;
-                    }
-                    let frequency_captures = date_regex
-                        .captures(time_range_captures.name("frequency").unwrap().as_str())
-                        .unwrap();
-                    let frequency_days = frequency_captures
-                        .name("day")
-                        .unwrap()
-                        .as_str()
-                        .parse::<u32>()
-                        .unwrap();
-                    let frequency_months = frequency_captures
-                        .name("month")
-                        .unwrap()
-                        .as_str()
-                        .parse::<u32>()
-                        .unwrap();
-                    let frequency_years = frequency_captures
-                        .name("day")
-                        .unwrap()
-                        .as_str()
-                        .parse::<u32>()
-                        .unwrap();
-                    let mut new_date = start_date;
-                    loop {
-                        new_date.add(frequency_days, frequency_months, frequency_years);
-                        if new_date > end_date {
-                            break;
-                        };
-                        invoice_dates.push(new_date);
-                    }
-                }
-                // Determine sender indices
-                let mut sender_acronyms: Vec<String> = Vec::new();
-                sender_acronyms.push(captures.name("first_sender").unwrap().as_str().to_owned());
-                for additional_sender in additional_sender_regex.captures_iter(&line) {
-                    sender_acronyms.push(
-                        additional_sender
-                            .name("additional_sender")
-                            .unwrap()
-                            .as_str()
-                            .to_owned(),
-                    );
-                }
-                let sender_indices: Vec<usize> = sender_acronyms
-                    .iter()
-                    .map(|acronym| account::find_index(&acronym, &accounts))
-                    .collect();
-                // Determine recipient indices
-                let mut recipient_acronyms: Vec<String> = Vec::new();
-                for recipient in recipient_regex.captures_iter(&line) {
-                    recipient_acronyms
-                        .push(recipient.name("recipient").unwrap().as_str().to_owned());
-                }
-                let recipient_indices: Vec<usize> = recipient_acronyms
-                    .iter()
-                    .map(|acronym| account::find_index(&acronym, &accounts))
-                    .collect();
-                // Add invoices
-                let total_amount = captures
-                    .name("amount")
-                    .unwrap()
-                    .as_str()
-                    .parse::<f64>()
-                    .unwrap();
-                let amount_per_sender = total_amount / sender_indices.len() as f64;
-                let note = captures.name("note").unwrap().as_str().to_owned();
-                for invoice_date in invoice_dates {
-                    // Sender -> first recipient
-                    for sender_index in &sender_indices {
-                        add_invoice(
-                            *sender_index,
-                            recipient_indices[0],
-                            amount_per_sender,
-                            invoice_date,
-                            note.clone(),
-                        );
-                    }
-                    // Additional recipients
-                    for i in 1..recipient_indices.len() {
-                        add_invoice(
-                            recipient_indices[i - 1],
-                            recipient_indices[i],
-                            total_amount,
-                            invoice_date,
-                            note.clone(),
-                        );
-                    }
-                }
-            } else if let Some(captures) = payment_regex.captures(&line) {
-                // Add payment
-                let sender_acronym = captures.name("sender").unwrap().as_str().to_owned();
-                let recipient_acronym = captures.name("recipient").unwrap().as_str().to_owned();
-                let date = Date::new(&captures);
-                if date > accounting_date {
-                    // Ignore payment
-                    continue;
-                }
-                payments.push(Transaction::new(
-                    account::find_index(&sender_acronym, &accounts),
-                    account::find_index(&recipient_acronym, &accounts),
-                    captures
-                        .name("amount")
-                        .unwrap()
-                        .as_str()
-                        .parse::<f64>()
-                        .unwrap(),
-                    date,
-                    captures.name("note").unwrap().as_str().to_owned(),
-                ));
-            } else {
-                panic!("Parsing error on line \"{}\"", line);
+            if let Err(error) = result {
+                parser.errors.push(error);
             }
         }
-        Self {
-            accounting_date: accounting_date,
-            accounts: accounts,
-            invoices: invoices,
-            payments: payments,
+        parser.finish();
+        if parser.accounting_date.is_none() && parser.errors.is_empty() {
+            parser.errors.push(ParseError::new(
+                1,
+                "",
+                0..0,
+                "missing header \"accounting_date dd.mm.yyyy\"".to_owned(),
+            ));
+        }
+
+        match parser.accounting_date {
+            Some(accounting_date) if parser.errors.is_empty() => Ok(Self {
+                accounting_date,
+                remainder_policy: parser.remainder_policy.unwrap_or_default(),
+                currency: parser.currency(),
+                rates: parser.rates,
+                accounts: parser.accounts,
+                invoices: parser.invoices,
+                payments: parser.payments,
+                shares: parser.shares,
+                entry_lines: parser.entry_lines,
+                warnings: parser.warnings,
+            }),
+            _ => Err(parser.errors),
         }
     }
+    /// Default date at which the ledger is evaluated. Recurring invoices
+    /// without an end date recur up to this date.
     pub fn accounting_date(&self) -> &Date {
         &self.accounting_date
     }
+    /// How leftover cents are assigned when an invoice is split.
+    pub fn remainder_policy(&self) -> RemainderPolicy {
+        self.remainder_policy
+    }
+    /// Currency all amounts are converted to.
+    pub fn currency(&self) -> &String {
+        &self.currency
+    }
+    /// Exchange rates in the order they are declared.
+    pub fn rates(&self) -> &Vec<ExchangeRate> {
+        &self.rates
+    }
+    /// Accounts in the order they are declared. Transactions refer to them by index.
     pub fn accounts(&self) -> &Vec<Account> {
         &self.accounts
     }
+    /// Invoices with recurring ones expanded, one per sender and recipient pair.
+    /// Includes invoices dated after the accounting date.
     pub fn invoices(&self) -> &Vec<Transaction> {
         &self.invoices
     }
+    /// Payments between accounts, including those dated after the accounting
+    /// date.
     pub fn payments(&self) -> &Vec<Transaction> {
         &self.payments
     }
+    // Invoices dated up to and including `date`, complete only up to the
+    // accounting date
+    pub(crate) fn invoices_as_of(&self, date: Date) -> impl Iterator<Item = &Transaction> {
+        self.invoices
+            .iter()
+            .filter(move |invoice| invoice.date() <= date)
+    }
+    // Payments dated up to and including `date`
+    pub(crate) fn payments_as_of(&self, date: Date) -> impl Iterator<Item = &Transaction> {
+        self.payments
+            .iter()
+            .filter(move |payment| payment.date() <= date)
+    }
+    /// Shares of the invoices borne by each sender, for breakdowns by
+    /// category.
+    pub fn shares(&self) -> &Vec<Share> {
+        &self.shares
+    }
+    /// Keeps only the invoices, payments and shares tagged with `tag`, e.g.
+    /// to settle the costs of a holiday on their own.
+    pub fn retain_tag(&mut self, tag: &str) {
+        self.invoices.retain(|invoice| invoice.has_tag(tag));
+        self.payments.retain(|payment| payment.has_tag(tag));
+        self.shares.retain(|share| share.has_tag(tag));
+    }
+    /// Problems which don't prevent evaluating the ledger, such as invoices
+    /// dated outside the tenancy of an account.
+    pub fn warnings(&self) -> &Vec<ParseError> {
+        &self.warnings
+    }
+    // Invoice, payment and opening lines which still take effect after the
+    // cut-off, in the order of the ledger. Recurring invoices start with
+    // their first occurrence after the cut-off.
+    pub(crate) fn lines_after(&self, cut_off: Date) -> Vec<String> {
+        self.entry_lines
+            .iter()
+            .flat_map(|entry_line| entry_line.lines_after(cut_off, &self.accounts))
+            .collect()
+    }
+}
+impl FromStr for Ledger {
+    type Err = LedgerError;
+
+    /// Parses a ledger from its text. Errors carry no file path.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Self::from_reader(string.as_bytes())
+    }
 }
 impl fmt::Display for Ledger {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut string = format!("Accounting date {}\n", &self.accounting_date)
-            .as_str()
-            .to_owned();
+        let mut string = format!("Accounting date {}\n", &self.accounting_date);
+        string += format!("Remainder policy {}\n", &self.remainder_policy).as_str();
+        string += format!("Currency {}\n", &self.currency).as_str();
+        if !self.rates.is_empty() {
+            string += "\nRates:\n";
+            for rate in &self.rates {
+                string += format!("{}\n", rate).as_str();
+            }
+        }
         string += "\nAccounts:\n";
         for account in &self.accounts {
             string += format!("{}\n", account).as_str();
         }
         string += "\nInvoices:\n";
         for invoice in &self.invoices {
-            string += format!("{}\n", invoice.to_string(&self.accounts)).as_str();
+            string += format!("{}\n", invoice.to_string(&self.accounts, &self.currency)).as_str();
         }
         string += "\nPayments:\n";
         for payment in &self.payments {
-            string += format!("{}\n", payment.to_string(&self.accounts)).as_str();
+            string += format!("{}\n", payment.to_string(&self.accounts, &self.currency)).as_str();
         }
         write!(f, "{}", string)
     }
 }
+
+// Invoice, payment or opening line as written in the ledger, kept to carry
+// it over into a closed ledger
+struct EntryLine {
+    line: String,
+    start_date: Date,
+    start_date_range: Range<usize>,
+    // Start date and recurrence of a recurring invoice, which are replaced
+    // by a single date for occurrences which cannot be re-anchored
+    schedule_range: Range<usize>,
+    recurrence: Option<Recurrence>,
+    end_date: Option<Date>,
+    // Position of `all` in an invoice split among all active accounts,
+    // which single occurrences replace by the senders weighted by their days
+    all_range: Option<Range<usize>>,
+}
+impl EntryLine {
+    fn single(line: &str, date: Date) -> Self {
+        EntryLine {
+            line: line.to_owned(),
+            start_date: date,
+            start_date_range: 0..0,
+            schedule_range: 0..0,
+            recurrence: None,
+            end_date: None,
+            all_range: None,
+        }
+    }
+    fn lines_after(&self, cut_off: Date, accounts: &[Account]) -> Vec<String> {
+        let recurrence = match self.recurrence {
+            Some(recurrence) if self.start_date <= cut_off => recurrence,
+            _ if self.start_date <= cut_off => return Vec::new(),
+            _ => return vec![self.line.clone()],
+        };
+        let is_due = |date: &Date| self.end_date.is_none_or(|end_date| *date <= end_date);
+        let mut lines: Vec<String> = Vec::new();
+        let mut occurrence = 1;
+        while let Some(date) = recurrence.nth(self.start_date, occurrence) {
+            if date > cut_off {
+                if !is_due(&date) {
+                    break;
+                }
+                // A date clamped at the end of a short month would shift the
+                // following occurrences, so it is written on its own
+                let shifted = (1..=12).any(|following| {
+                    recurrence.nth(date, following)
+                        != recurrence.nth(self.start_date, occurrence + following)
+                });
+                if !shifted || lines.len() >= MAX_SINGLE_OCCURRENCES {
+                    lines.push(self.replace(&self.start_date_range, date));
+                    break;
+                }
+                lines.push(self.single_occurrence(recurrence, occurrence, date, accounts));
+            }
+            occurrence += 1;
+        }
+        lines
+    }
+    // Occurrence as an invoice of its own, split like in the original line
+    fn single_occurrence(
+        &self,
+        recurrence: Recurrence,
+        occurrence: u32,
+        date: Date,
+        accounts: &[Account],
+    ) -> String {
+        let line = self.replace(&self.schedule_range, date);
+        let all_range = match &self.all_range {
+            Some(all_range) => all_range,
+            None => return line,
+        };
+        let weights = active_days(
+            accounts,
+            self.start_date,
+            Some(recurrence),
+            occurrence,
+            date,
+        );
+        let senders: Vec<String> = accounts
+            .iter()
+            .zip(weights)
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(account, weight)| format!("{}*{}", account.acronym(), weight))
+            .collect();
+        format!(
+            "{}{}{}",
+            &line[..all_range.start],
+            senders.join(" : "),
+            &line[all_range.end..]
+        )
+    }
+    fn replace(&self, range: &Range<usize>, date: Date) -> String {
+        format!(
+            "{}{}{}",
+            &self.line[..range.start],
+            date,
+            &self.line[range.end..]
+        )
+    }
+}
+
+const TOTAL_TOO_LARGE: &str = "amounts add up to more than the supported maximum";
+
+// Note of opening balances without a note of their own
+const OPENING_NOTE: &str = "Übertrag";
+
+// Tag of payments settling up after moving out, which are not checked
+// against the end of the tenancy
+const SETTLE_UP_TAG: &str = "settle-up";
+
+// Occurrences written on their own before a recurring invoice is re-anchored
+const MAX_SINGLE_OCCURRENCES: usize = 12;
+
+// Days each account is active from the occurrence of an invoice up to the
+// next one, or on the date of an invoice which does not recur
+fn active_days(
+    accounts: &[Account],
+    start_date: Date,
+    recurrence: Option<Recurrence>,
+    occurrence: u32,
+    date: Date,
+) -> Vec<u64> {
+    let period_end = match recurrence {
+        Some(recurrence) => recurrence.nth(start_date, occurrence + 1),
+        None => date.checked_add(1, 0, 0),
+    }
+    .unwrap_or(date);
+    accounts
+        .iter()
+        .map(|account| account.active_days(date, period_end))
+        .collect()
+}
+
+// Invoice or payment as written in the ledger. Amounts in other currencies
+// can only be converted once all exchange rates are known.
+struct PendingTransaction {
+    line_number: usize,
+    line: String,
+    amount_range: Range<usize>,
+    dates: Vec<Date>,
+    // Empty if the invoice is split among all active accounts
+    sender_indices: Vec<usize>,
+    sender_weights: Vec<u64>,
+    all_active: bool,
+    senders_range: Range<usize>,
+    recurrence: Option<Recurrence>,
+    recipient_indices: Vec<usize>,
+    // Positions of the senders followed by the recipients in the line
+    account_ranges: Vec<Range<usize>>,
+    amount: Money,
+    currency: Option<String>,
+    note: String,
+}
+
+impl PendingTransaction {
+    fn amount_error(&self, reason: String) -> ParseError {
+        ParseError::new(
+            self.line_number,
+            &self.line,
+            self.amount_range.clone(),
+            reason,
+        )
+    }
+}
+
+struct Parser {
+    comment_regex: Regex,
+    date_regex: Regex,
+    acronym_regex: Regex,
+    share_regex: Regex,
+    header_regex: Regex,
+    remainder_regex: Regex,
+    currency_regex: Regex,
+    rate_regex: Regex,
+    attribute_regex: Regex,
+    attribute_line_regex: Regex,
+    account_regex: Regex,
+    invoice_regex: Regex,
+    payment_regex: Regex,
+    opening_regex: Regex,
+    as_of: Option<Date>,
+    accounting_date: Option<Date>,
+    remainder_policy: Option<RemainderPolicy>,
+    currency: Option<String>,
+    rates: Vec<ExchangeRate>,
+    accounts: Vec<Account>,
+    attribute_account_index: Option<usize>,
+    pending_invoices: Vec<PendingTransaction>,
+    pending_payments: Vec<PendingTransaction>,
+    invoices: Vec<Transaction>,
+    // Index of the invoice per unordered pair of accounts, date and note
+    invoice_indices: HashMap<(usize, usize, Date, String), usize>,
+    payments: Vec<Transaction>,
+    shares: Vec<Share>,
+    entry_lines: Vec<EntryLine>,
+    errors: Vec<ParseError>,
+    warnings: Vec<ParseError>,
+}
+impl Parser {
+    fn new(as_of: Option<Date>) -> Self {
+        // Regex pattern strings
+        let comment_pattern = r"^\s*//";
+        let date_pattern = r"(?P<day>\d{1,2})\.(?P<month>\d{1,2})\.(?P<year>\d{4})";
+        let acronym_pattern = r"[A-Z]{2}";
+        let share_pattern = r"(?P<acronym>[A-Z]{2})(?:\*(?P<weight>\d+(?:\.\d+)?)|\s(?P<percentage>\d+(?:\.\d+)?)%)?";
+        let sender_pattern = r"[A-Z]{2}(?:\*\d+(?:\.\d+)?|\s\d+(?:\.\d+)?%)?";
+        let amount_pattern = r"(?P<amount>\d+(?:\.\d+)?)(?:\s(?P<currency>[A-Z]{3}))?";
+        let frequency_pattern =
+            r"\d{1,2}\.\d{1,2}\.\d{4}|daily|weekly|monthly|quarterly|yearly|every\s\d+\s[a-z]+";
+        let note_pattern = r"(?P<note>.+)";
+        let header_pattern = r"^accounting_date\s(?P<date>".to_owned() + date_pattern + r")$";
+        let remainder_pattern = r"^remainder\s(?P<policy>\S+)$";
+        let currency_pattern = r"^currency\s(?P<currency>[A-Z]{3})$";
+        let rate_pattern =
+            r"^rate\s(?P<from>[A-Z]{3})\s(?P<to>[A-Z]{3})\s(?P<rate>\d+(?:\.\d+)?)\s(?P<date>"
+                .to_owned()
+                + date_pattern
+                + r")$";
+        let attribute_pattern = r#"\s(?P<key>[a-z_]+)=(?:"(?P<quoted>[^"]*)"|(?P<value>[^\s"]+))"#;
+        let attribute_line_pattern =
+            r#"^\s+(?P<key>[a-z_]+)=(?:"(?P<quoted>[^"]*)"|(?P<value>.+))$"#;
+        let account_pattern = r#"^account\s(?P<acronym>[A-Z]{2})\s(?P<name>.+?)(?P<attributes>(?:\s[a-z_]+=(?:"[^"]*"|[^\s"]+))*)$"#;
+        let invoice_pattern = r"^invoice\s(?P<senders>all|".to_owned()
+            + sender_pattern
+            + r"(?:\s:\s"
+            + sender_pattern
+            + r")*)(?P<recipients>(?:\s->\s[A-Z]{2})+)\s"
+            + amount_pattern
+            + r"\s(?:(?P<start_date>\d{1,2}\.\d{1,2}\.\d{4})(?:\s-(?:\s(?P<end_date>\d{1,2}\.\d{1,2}\.\d{4}))?\s:\s(?P<frequency>"
+            + frequency_pattern
+            + r"))?|(?P<open_schedule>from\s(?P<open_start_date>\d{1,2}\.\d{1,2}\.\d{4})\s(?P<open_frequency>"
+            + frequency_pattern
+            + r")))\s"
+            + note_pattern;
+        let payment_pattern = r"^payment\s(?P<sender>[A-Z]{2})\s->\s(?P<recipient>[A-Z]{2})\s"
+            .to_owned()
+            + amount_pattern
+            + r"\s(?P<date>"
+            + date_pattern
+            + r")\s"
+            + note_pattern;
+        let opening_pattern =
+            r"^opening\s(?P<debtor>[A-Z]{2})\s->\s(?P<creditor>[A-Z]{2})\s(?P<amount>\d+(?:\.\d+)?)\s(?P<date>"
+                .to_owned()
+                + date_pattern
+                + r")(?:\s(?P<note>.+))?$";
+
+        // Regex objects
+        Parser {
+            comment_regex: Regex::new(comment_pattern).unwrap(),
+            date_regex: Regex::new(date_pattern).unwrap(),
+            acronym_regex: Regex::new(acronym_pattern).unwrap(),
+            share_regex: Regex::new(share_pattern).unwrap(),
+            header_regex: Regex::new(header_pattern.as_str()).unwrap(),
+            remainder_regex: Regex::new(remainder_pattern).unwrap(),
+            currency_regex: Regex::new(currency_pattern).unwrap(),
+            rate_regex: Regex::new(rate_pattern.as_str()).unwrap(),
+            attribute_regex: Regex::new(attribute_pattern).unwrap(),
+            attribute_line_regex: Regex::new(attribute_line_pattern).unwrap(),
+            account_regex: Regex::new(account_pattern).unwrap(),
+            invoice_regex: Regex::new(invoice_pattern.as_str()).unwrap(),
+            payment_regex: Regex::new(payment_pattern.as_str()).unwrap(),
+            opening_regex: Regex::new(opening_pattern.as_str()).unwrap(),
+            as_of,
+            accounting_date: None,
+            remainder_policy: None,
+            currency: None,
+            rates: Vec::new(),
+            accounts: Vec::new(),
+            attribute_account_index: None,
+            pending_invoices: Vec::new(),
+            pending_payments: Vec::new(),
+            invoices: Vec::new(),
+            invoice_indices: HashMap::new(),
+            payments: Vec::new(),
+            shares: Vec::new(),
+            entry_lines: Vec::new(),
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn parse_line(&mut self, line_number: usize, line: &str) -> Result<(), ParseError> {
+        // Indented attribute lines belong to the account right above them
+        let previous_account_index = self.attribute_account_index.take();
+        if line_number == 1 {
+            // Parse header
+            let header_captures = self.header_regex.captures(line).ok_or_else(|| {
+                ParseError::whole_line(
+                    line_number,
+                    line,
+                    "expected header \"accounting_date dd.mm.yyyy\"".to_owned(),
+                )
+            })?;
+            let accounting_date =
+                self.parse_date(line_number, line, header_captures.name("date").unwrap())?;
+            // The accounting date can be overridden to evaluate the ledger at another date
+            self.accounting_date = Some(self.as_of.unwrap_or(accounting_date));
+        } else if line.is_empty() {
+            // Ignore empty line
+        } else if self.comment_regex.is_match(line) {
+            // Ignore comment, attribute lines may continue after it
+            self.attribute_account_index = previous_account_index;
+        } else if let Some(captures) = self.remainder_regex.captures(line) {
+            // Set the policy for splitting invoices among several senders
+            let policy = captures.name("policy").unwrap();
+            if self.remainder_policy.is_some() {
+                return Err(ParseError::whole_line(
+                    line_number,
+                    line,
+                    "duplicate remainder setting".to_owned(),
+                ));
+            }
+            if !self.pending_invoices.is_empty() {
+                return Err(ParseError::whole_line(
+                    line_number,
+                    line,
+                    "remainder setting must precede all invoices".to_owned(),
+                ));
+            }
+            self.remainder_policy =
+                Some(policy.as_str().parse().map_err(|reason| {
+                    ParseError::new(line_number, line, policy.range(), reason)
+                })?);
+        } else if let Some(captures) = self.currency_regex.captures(line) {
+            // Set the currency all amounts are converted to
+            if self.currency.is_some() {
+                return Err(ParseError::whole_line(
+                    line_number,
+                    line,
+                    "duplicate currency setting".to_owned(),
+                ));
+            }
+            self.currency = Some(captures.name("currency").unwrap().as_str().to_owned());
+        } else if let Some(captures) = self.rate_regex.captures(line) {
+            // Add exchange rate
+            let from = captures.name("from").unwrap().as_str().to_owned();
+            let to = captures.name("to").unwrap().as_str().to_owned();
+            if from == to {
+                return Err(ParseError::whole_line(
+                    line_number,
+                    line,
+                    format!("exchange rate from {} to itself", from),
+                ));
+            }
+            let date = self.parse_date(line_number, line, captures.name("date").unwrap())?;
+            let rate_match = captures.name("rate").unwrap();
+            self.rates.push(
+                ExchangeRate::new(from, to, rate_match.as_str(), date).map_err(|reason| {
+                    ParseError::new(line_number, line, rate_match.range(), reason)
+                })?,
+            );
+        } else if let Some(captures) = self.account_regex.captures(line) {
+            // Add new account
+            let acronym = captures.name("acronym").unwrap();
+            if account::find_index(acronym.as_str(), &self.accounts).is_some() {
+                return Err(ParseError::new(
+                    line_number,
+                    line,
+                    acronym.range(),
+                    format!("duplicate account {}", acronym.as_str()),
+                ));
+            }
+            let mut account = Account::new(&captures);
+            let attributes = captures.name("attributes").unwrap();
+            let mut result = Ok(());
+            for attribute_captures in self.attribute_regex.captures_iter(attributes.as_str()) {
+                let attribute = attribute_captures.get(0).unwrap();
+                let key = attribute_captures.name("key").unwrap();
+                let range = attributes.start() + key.start()..attributes.start() + attribute.end();
+                if let Err(error) =
+                    Self::set_attribute(&mut account, line_number, line, range, &attribute_captures)
+                {
+                    result = result.and(Err(error));
+                }
+            }
+            // Keep the account even if an attribute is invalid to avoid
+            // follow-up errors on lines which use it
+            self.accounts.push(account);
+            self.attribute_account_index = Some(self.accounts.len() - 1);
+            result?;
+        } else if let Some(captures) = self.attribute_line_regex.captures(line) {
+            // Add attribute to the preceding account
+            let account_index = previous_account_index.ok_or_else(|| {
+                ParseError::whole_line(
+                    line_number,
+                    line,
+                    "account attribute without preceding account".to_owned(),
+                )
+            })?;
+            self.attribute_account_index = Some(account_index);
+            let range = captures.name("key").unwrap().start()..line.len();
+            Self::set_attribute(
+                &mut self.accounts[account_index],
+                line_number,
+                line,
+                range,
+                &captures,
+            )?;
+        } else if let Some(captures) = self.invoice_regex.captures(line) {
+            self.parse_invoice(line_number, line, &captures)?;
+        } else if let Some(captures) = self.payment_regex.captures(line) {
+            self.parse_payment(line_number, line, &captures)?;
+        } else if let Some(captures) = self.opening_regex.captures(line) {
+            self.parse_opening(line_number, line, &captures)?;
+        } else {
+            return Err(ParseError::whole_line(
+                line_number,
+                line,
+                "unrecognized line".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn set_attribute(
+        account: &mut Account,
+        line_number: usize,
+        line: &str,
+        range: Range<usize>,
+        captures: &regex::Captures,
+    ) -> Result<(), ParseError> {
+        let value = captures
+            .name("quoted")
+            .or_else(|| captures.name("value"))
+            .unwrap();
+        account
+            .set_attribute(
+                captures.name("key").unwrap().as_str(),
+                value.as_str().trim(),
+            )
+            .map_err(|reason| ParseError::new(line_number, line, range, reason))
+    }
+
+    fn parse_invoice(
+        &mut self,
+        line_number: usize,
+        line: &str,
+        captures: &regex::Captures,
+    ) -> Result<(), ParseError> {
+        // Determine invoice dates
+        let start_date_match = captures
+            .name("start_date")
+            .or_else(|| captures.name("open_start_date"))
+            .unwrap();
+        let start_date = self.parse_date(line_number, line, start_date_match)?;
+        let mut invoice_dates: Vec<Date> = vec![start_date];
+        let mut recurrence: Option<Recurrence> = None;
+        let mut explicit_end_date: Option<Date> = None;
+        let frequency_match = captures
+            .name("frequency")
+            .or_else(|| captures.name("open_frequency"));
+        if let Some(frequency_match) = frequency_match {
+            // Without an end date the invoice recurs up to the accounting date
+            let mut end_date = self.accounting_date;
+            if let Some(end_date_match) = captures.name("end_date") {
+                let parsed_end_date = self.parse_date(line_number, line, end_date_match)?;
+                if parsed_end_date < start_date {
+                    return Err(ParseError::new(
+                        line_number,
+                        line,
+                        end_date_match.range(),
+                        "end date before start date".to_owned(),
+                    ));
+                }
+                end_date = Some(parsed_end_date);
+                explicit_end_date = Some(parsed_end_date);
+            }
+            let parsed_recurrence =
+                frequency_match
+                    .as_str()
+                    .parse::<Recurrence>()
+                    .map_err(|reason| {
+                        ParseError::new(line_number, line, frequency_match.range(), reason)
+                    })?;
+            if let Some(end_date) = end_date.filter(|&end_date| start_date <= end_date) {
+                invoice_dates = parsed_recurrence
+                    .dates(start_date, end_date)
+                    .ok_or_else(|| {
+                        ParseError::new(
+                            line_number,
+                            line,
+                            frequency_match.range(),
+                            "recurrence exceeds the supported date range".to_owned(),
+                        )
+                    })?;
+            }
+            recurrence = Some(parsed_recurrence);
+        }
+        // Determine sender and recipient indices, the senders of an invoice
+        // split among all active accounts are determined per date
+        let senders_match = captures.name("senders").unwrap();
+        let all_active = senders_match.as_str() == "all";
+        let (sender_indices, sender_weights) = if all_active {
+            (Vec::new(), Vec::new())
+        } else {
+            (
+                self.find_account_indices(line_number, line, senders_match)?,
+                self.parse_sender_weights(line_number, line, senders_match)?,
+            )
+        };
+        let recipient_indices =
+            self.find_account_indices(line_number, line, captures.name("recipients").unwrap())?;
+        let amount = self.parse_amount(line_number, line, captures.name("amount").unwrap())?;
+        // The schedule of the open form includes the "from" keyword
+        let schedule_range = match captures.name("open_schedule") {
+            Some(schedule_match) => schedule_match.range(),
+            None => {
+                start_date_match.start()
+                    ..frequency_match.map_or(start_date_match.end(), |m| m.end())
+            }
+        };
+        self.entry_lines.push(EntryLine {
+            line: line.to_owned(),
+            start_date,
+            start_date_range: start_date_match.range(),
+            schedule_range,
+            recurrence,
+            end_date: explicit_end_date,
+            all_range: all_active.then(|| senders_match.range()),
+        });
+        self.pending_invoices.push(PendingTransaction {
+            line_number,
+            line: line.to_owned(),
+            amount_range: Self::amount_range(captures),
+            dates: invoice_dates,
+            sender_indices,
+            sender_weights,
+            all_active,
+            senders_range: senders_match.range(),
+            recurrence,
+            recipient_indices,
+            account_ranges: self
+                .account_ranges(senders_match)
+                .chain(self.account_ranges(captures.name("recipients").unwrap()))
+                .collect(),
+            amount,
+            currency: captures.name("currency").map(|m| m.as_str().to_owned()),
+            note: captures.name("note").unwrap().as_str().to_owned(),
+        });
+        Ok(())
+    }
+
+    fn parse_payment(
+        &mut self,
+        line_number: usize,
+        line: &str,
+        captures: &regex::Captures,
+    ) -> Result<(), ParseError> {
+        let sender_index =
+            self.find_account_index(line_number, line, captures.name("sender").unwrap())?;
+        let recipient_index =
+            self.find_account_index(line_number, line, captures.name("recipient").unwrap())?;
+        if sender_index == recipient_index {
+            return Err(ParseError::whole_line(
+                line_number,
+                line,
+                "payment to the paying account itself".to_owned(),
+            ));
+        }
+        let date = self.parse_date(line_number, line, captures.name("date").unwrap())?;
+        let amount = self.parse_amount(line_number, line, captures.name("amount").unwrap())?;
+        self.entry_lines.push(EntryLine::single(line, date));
+        self.pending_payments.push(PendingTransaction {
+            line_number,
+            line: line.to_owned(),
+            amount_range: Self::amount_range(captures),
+            dates: vec![date],
+            sender_indices: vec![sender_index],
+            sender_weights: vec![100],
+            all_active: false,
+            senders_range: captures.name("sender").unwrap().range(),
+            recurrence: None,
+            recipient_indices: vec![recipient_index],
+            account_ranges: vec![
+                captures.name("sender").unwrap().range(),
+                captures.name("recipient").unwrap().range(),
+            ],
+            amount,
+            currency: captures.name("currency").map(|m| m.as_str().to_owned()),
+            note: captures.name("note").unwrap().as_str().to_owned(),
+        });
+        Ok(())
+    }
+
+    // Opening balance owed by the debtor to the creditor, e.g. carried over
+    // from a closed ledger
+    fn parse_opening(
+        &mut self,
+        line_number: usize,
+        line: &str,
+        captures: &regex::Captures,
+    ) -> Result<(), ParseError> {
+        let debtor_index =
+            self.find_account_index(line_number, line, captures.name("debtor").unwrap())?;
+        let creditor_index =
+            self.find_account_index(line_number, line, captures.name("creditor").unwrap())?;
+        if debtor_index == creditor_index {
+            return Err(ParseError::whole_line(
+                line_number,
+                line,
+                "opening balance of an account with itself".to_owned(),
+            ));
+        }
+        let date = self.parse_date(line_number, line, captures.name("date").unwrap())?;
+        let amount = self.parse_amount(line_number, line, captures.name("amount").unwrap())?;
+        let note = captures
+            .name("note")
+            .map_or(OPENING_NOTE, |m| m.as_str())
+            .to_owned();
+        self.entry_lines.push(EntryLine::single(line, date));
+        self.add_invoice(debtor_index, creditor_index, amount, date, note, None)
+            .map_err(|reason| {
+                ParseError::new(
+                    line_number,
+                    line,
+                    captures.name("amount").unwrap().range(),
+                    reason,
+                )
+            })
+    }
+
+    fn account_ranges<'a>(
+        &'a self,
+        acronyms_match: Match<'a>,
+    ) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.acronym_regex
+            .find_iter(acronyms_match.as_str())
+            .map(move |acronym_match| {
+                acronyms_match.start() + acronym_match.start()
+                    ..acronyms_match.start() + acronym_match.end()
+            })
+    }
+
+    fn amount_range(captures: &regex::Captures) -> Range<usize> {
+        let amount_match = captures.name("amount").unwrap();
+        match captures.name("currency") {
+            Some(currency_match) => amount_match.start()..currency_match.end(),
+            None => amount_match.range(),
+        }
+    }
+
+    fn currency(&self) -> String {
+        self.currency
+            .clone()
+            .unwrap_or_else(|| currency::DEFAULT_CURRENCY.to_owned())
+    }
+
+    fn finish(&mut self) {
+        // Convert and add all invoices and payments in the order of the ledger
+        for pending_invoice in mem::take(&mut self.pending_invoices) {
+            self.check_tenancy(&pending_invoice, false);
+            if let Err(error) = self.add_pending_invoice(&pending_invoice) {
+                self.errors.push(error);
+            }
+        }
+        for pending_payment in mem::take(&mut self.pending_payments) {
+            let settling_up = transaction::parse_tags(&pending_payment.note)
+                .iter()
+                .any(|tag| tag == SETTLE_UP_TAG);
+            self.check_tenancy(&pending_payment, settling_up);
+            match self.convert(&pending_payment, pending_payment.dates[0]) {
+                Ok((amount, original_amount)) => self.payments.push(
+                    Transaction::new(
+                        pending_payment.sender_indices[0],
+                        pending_payment.recipient_indices[0],
+                        amount,
+                        pending_payment.dates[0],
+                        pending_payment.note,
+                    )
+                    .with_original_amount(original_amount),
+                ),
+                Err(error) => self.errors.push(error),
+            }
+        }
+        // All sums of amounts, such as balances, fit if the total does
+        let total = self
+            .invoices
+            .iter()
+            .chain(&self.payments)
+            .map(|transaction| transaction.amount())
+            .chain(self.shares.iter().map(|share| share.amount()))
+            .try_fold(Money::ZERO, |total, amount| total.checked_add(amount.abs()));
+        if total.is_none() {
+            self.errors
+                .push(ParseError::file(TOTAL_TOO_LARGE.to_owned()));
+        }
+    }
+
+    // Invoices and payments may only involve accounts within their tenancy,
+    // except for payments settling up after moving out
+    fn check_tenancy(&mut self, pending: &PendingTransaction, settling_up: bool) {
+        let sender_count = pending.sender_indices.len();
+        let involved_indices = pending
+            .sender_indices
+            .iter()
+            .zip(&pending.sender_weights)
+            .map(|(sender_index, &weight)| (sender_index, weight > 0))
+            .chain(pending.recipient_indices.iter().map(|index| (index, true)));
+        for (position, (&account_index, involved)) in involved_indices.enumerate() {
+            let account = &self.accounts[account_index];
+            let inactive_dates: Vec<Date> = pending
+                .dates
+                .iter()
+                .copied()
+                .filter(|&date| {
+                    let moved_out = account.active_until().is_some_and(|until| date > until);
+                    !(account.is_active(date) || settling_up && moved_out)
+                })
+                .collect();
+            if !involved || inactive_dates.is_empty() {
+                continue;
+            }
+            let dates = match inactive_dates[..] {
+                [date] => format!("on {}", date),
+                _ => format!(
+                    "on {} dates from {} to {}",
+                    inactive_dates.len(),
+                    inactive_dates[0],
+                    inactive_dates[inactive_dates.len() - 1]
+                ),
+            };
+            let tenancy = match (account.active_from(), account.active_until()) {
+                (Some(active_from), Some(active_until)) => {
+                    format!("from {} until {}", active_from, active_until)
+                }
+                (Some(active_from), None) => format!("from {}", active_from),
+                (None, Some(active_until)) => format!("until {}", active_until),
+                (None, None) => unreachable!(),
+            };
+            let role = if position < sender_count {
+                "sender"
+            } else {
+                "recipient"
+            };
+            self.warnings.push(ParseError::new(
+                pending.line_number,
+                &pending.line,
+                pending.account_ranges[position].clone(),
+                format!(
+                    "warning: {} {} is not active {} (active {})",
+                    role,
+                    account.acronym(),
+                    dates,
+                    tenancy
+                ),
+            ));
+        }
+    }
+
+    fn add_pending_invoice(&mut self, pending: &PendingTransaction) -> Result<(), ParseError> {
+        let recipient_indices = &pending.recipient_indices;
+        for (occurrence, &invoice_date) in pending.dates.iter().enumerate() {
+            let (sender_indices, sender_weights) = if pending.all_active {
+                self.active_senders(pending, occurrence as u32, invoice_date)?
+            } else {
+                (
+                    pending.sender_indices.clone(),
+                    pending.sender_weights.clone(),
+                )
+            };
+            let priority = self
+                .remainder_policy
+                .unwrap_or_default()
+                .priority(&sender_indices, recipient_indices[0]);
+            let (total_amount, original_total_amount) = self.convert(pending, invoice_date)?;
+            let sender_amounts = allocation::allocate(total_amount, &sender_weights, &priority);
+            // Foreign amounts are split the same way to show each share as written
+            let original_sender_amounts: Vec<Option<ForeignAmount>> = match &original_total_amount {
+                Some(original) => {
+                    allocation::allocate(original.amount(), &sender_weights, &priority)
+                        .into_iter()
+                        .map(|amount| Some(ForeignAmount::new(amount, original.currency().clone())))
+                        .collect()
+                }
+                None => vec![None; sender_indices.len()],
+            };
+            // Sender -> first recipient, senders with a zero share are skipped
+            for ((sender_index, sender_amount), original_sender_amount) in sender_indices
+                .iter()
+                .zip(sender_amounts)
+                .zip(original_sender_amounts)
+            {
+                if sender_amount.is_zero() {
+                    continue;
+                }
+                self.shares.push(Share::new(
+                    *sender_index,
+                    sender_amount,
+                    invoice_date,
+                    &pending.note,
+                ));
+                self.add_invoice(
+                    *sender_index,
+                    recipient_indices[0],
+                    sender_amount,
+                    invoice_date,
+                    pending.note.clone(),
+                    original_sender_amount,
+                )
+                .map_err(|reason| pending.amount_error(reason))?;
+            }
+            // Additional recipients
+            for recipient_pair in recipient_indices.windows(2) {
+                self.add_invoice(
+                    recipient_pair[0],
+                    recipient_pair[1],
+                    total_amount,
+                    invoice_date,
+                    pending.note.clone(),
+                    original_total_amount.clone(),
+                )
+                .map_err(|reason| pending.amount_error(reason))?;
+            }
+        }
+        Ok(())
+    }
+
+    // All accounts weighted by the days they are active from the given
+    // occurrence up to the next one
+    fn active_senders(
+        &self,
+        pending: &PendingTransaction,
+        occurrence: u32,
+        date: Date,
+    ) -> Result<(Vec<usize>, Vec<u64>), ParseError> {
+        let sender_indices: Vec<usize> = (0..self.accounts.len()).collect();
+        let sender_weights = active_days(
+            &self.accounts,
+            pending.dates[0],
+            pending.recurrence,
+            occurrence,
+            date,
+        );
+        if sender_weights.iter().all(|&weight| weight == 0) {
+            return Err(ParseError::new(
+                pending.line_number,
+                &pending.line,
+                pending.senders_range.clone(),
+                format!("no account is active in the period starting {}", date),
+            ));
+        }
+        Ok((sender_indices, sender_weights))
+    }
+
+    fn convert(
+        &self,
+        pending: &PendingTransaction,
+        date: Date,
+    ) -> Result<(Money, Option<ForeignAmount>), ParseError> {
+        let currency = self.currency();
+        match &pending.currency {
+            Some(from) if *from != currency => {
+                let amount = currency::convert(pending.amount, from, &currency, date, &self.rates)
+                    .map_err(|reason| pending.amount_error(reason))?;
+                Ok((
+                    amount,
+                    Some(ForeignAmount::new(pending.amount, from.clone())),
+                ))
+            }
+            _ => Ok((pending.amount, None)),
+        }
+    }
+
+    fn add_invoice(
+        &mut self,
+        sender_index: usize,
+        recipient_index: usize,
+        amount: Money,
+        date: Date,
+        note: String,
+        original_amount: Option<ForeignAmount>,
+    ) -> Result<(), String> {
+        if sender_index != recipient_index {
+            let mut actual_amount = amount;
+            let mut actual_original_amount = original_amount;
+            let key = (
+                sender_index.min(recipient_index),
+                sender_index.max(recipient_index),
+                date,
+                note.clone(),
+            );
+            let existing_index = self.invoice_indices.get(&key).copied();
+            if let Some(existing_index) = existing_index {
+                // This invoice exists already
+                let existing_invoice_clone = &self.invoices[existing_index];
+                // Add the amount of the existing invoice to the new invoice
+                let same_direction = existing_invoice_clone.sender_index() == sender_index;
+                actual_amount = if same_direction {
+                    actual_amount.checked_add(existing_invoice_clone.amount())
+                } else {
+                    actual_amount.checked_sub(existing_invoice_clone.amount())
+                }
+                .ok_or_else(|| TOTAL_TOO_LARGE.to_owned())?;
+                // Original amounts can only be added up in the same currency
+                actual_original_amount = match (
+                    actual_original_amount,
+                    existing_invoice_clone.original_amount(),
+                ) {
+                    (Some(original), Some(existing_original))
+                        if original.currency() == existing_original.currency() =>
+                    {
+                        let original_sum = if same_direction {
+                            original.amount().checked_add(existing_original.amount())
+                        } else {
+                            original.amount().checked_sub(existing_original.amount())
+                        }
+                        .ok_or_else(|| TOTAL_TOO_LARGE.to_owned())?;
+                        Some(ForeignAmount::new(
+                            original_sum,
+                            original.currency().clone(),
+                        ))
+                    }
+                    _ => None,
+                };
+            }
+            let invoice =
+                Transaction::new(sender_index, recipient_index, actual_amount, date, note)
+                    .with_original_amount(actual_original_amount);
+            match existing_index {
+                // Replace the existing invoice
+                Some(existing_index) => self.invoices[existing_index] = invoice,
+                None => {
+                    self.invoice_indices.insert(key, self.invoices.len());
+                    self.invoices.push(invoice);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_date(
+        &self,
+        line_number: usize,
+        line: &str,
+        date_match: Match,
+    ) -> Result<Date, ParseError> {
+        let captures = self.date_regex.captures(date_match.as_str()).unwrap();
+        Date::new(&captures).ok_or_else(|| {
+            ParseError::new(
+                line_number,
+                line,
+                date_match.range(),
+                format!("invalid date {}", date_match.as_str()),
+            )
+        })
+    }
+
+    fn parse_amount(
+        &self,
+        line_number: usize,
+        line: &str,
+        amount_match: Match,
+    ) -> Result<Money, ParseError> {
+        amount_match
+            .as_str()
+            .parse::<Money>()
+            .map_err(|reason| ParseError::new(line_number, line, amount_match.range(), reason))
+    }
+
+    fn parse_sender_weights(
+        &self,
+        line_number: usize,
+        line: &str,
+        senders_match: Match,
+    ) -> Result<Vec<u64>, ParseError> {
+        let mut weights: Vec<u64> = Vec::new();
+        let mut percentage_count = 0;
+        for share_captures in self.share_regex.captures_iter(senders_match.as_str()) {
+            let (weight_match, is_percentage) = match (
+                share_captures.name("weight"),
+                share_captures.name("percentage"),
+            ) {
+                (Some(weight_match), _) => (weight_match, false),
+                (_, Some(percentage_match)) => (percentage_match, true),
+                (None, None) => {
+                    // Unweighted senders have a weight of 1
+                    weights.push(100);
+                    continue;
+                }
+            };
+            let start = senders_match.start() + weight_match.start();
+            let weight = allocation::parse_weight(weight_match.as_str()).map_err(|reason| {
+                ParseError::new(line_number, line, start..start + weight_match.len(), reason)
+            })?;
+            if is_percentage {
+                percentage_count += 1;
+            }
+            weights.push(weight);
+        }
+        if percentage_count > 0 {
+            if percentage_count != weights.len() {
+                return Err(ParseError::new(
+                    line_number,
+                    line,
+                    senders_match.range(),
+                    "either all or no senders need a percentage".to_owned(),
+                ));
+            }
+            let percentage_sum: u128 = weights.iter().map(|&weight| weight as u128).sum();
+            if percentage_sum != 100 * 100 {
+                return Err(ParseError::new(
+                    line_number,
+                    line,
+                    senders_match.range(),
+                    format!(
+                        "percentages sum up to {}.{:02}%, expected 100%",
+                        percentage_sum / 100,
+                        percentage_sum % 100
+                    ),
+                ));
+            }
+        } else if weights.iter().all(|&weight| weight == 0) {
+            return Err(ParseError::new(
+                line_number,
+                line,
+                senders_match.range(),
+                "at least one sender needs a weight above zero".to_owned(),
+            ));
+        }
+        Ok(weights)
+    }
+
+    fn find_account_index(
+        &self,
+        line_number: usize,
+        line: &str,
+        acronym_match: Match,
+    ) -> Result<usize, ParseError> {
+        account::find_index(acronym_match.as_str(), &self.accounts).ok_or_else(|| {
+            ParseError::new(
+                line_number,
+                line,
+                acronym_match.range(),
+                format!("unknown account {}", acronym_match.as_str()),
+            )
+        })
+    }
+
+    fn find_account_indices(
+        &self,
+        line_number: usize,
+        line: &str,
+        acronyms_match: Match,
+    ) -> Result<Vec<usize>, ParseError> {
+        self.acronym_regex
+            .find_iter(acronyms_match.as_str())
+            .map(|acronym_match| {
+                let start = acronyms_match.start() + acronym_match.start();
+                let acronym = acronym_match.as_str();
+                account::find_index(acronym, &self.accounts).ok_or_else(|| {
+                    ParseError::new(
+                        line_number,
+                        line,
+                        start..start + acronym.len(),
+                        format!("unknown account {}", acronym),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEDGER: &str = "accounting_date 31.12.2024
+account AB Anna
+account CD Carl
+
+invoice AB -> CD 100.00 31.01.2024 - 31.12.2024 : monthly Rent
+invoice CD -> AB 10.00 from 31.01.2024 monthly Internet
+payment AB -> CD 50.00 15.03.2024 Cash
+";
+
+    fn errors(text: &str) -> Vec<String> {
+        match text.parse::<Ledger>() {
+            Ok(_) => Vec::new(),
+            Err(error) => error
+                .errors()
+                .iter()
+                .map(|error| error.to_string())
+                .collect(),
+        }
+    }
+
+    fn lines_after(cut_off: &str) -> Vec<String> {
+        let ledger: Ledger = LEDGER.parse().unwrap();
+        ledger.lines_after(cut_off.parse().unwrap())
+    }
+
+    #[test]
+    fn lines_after_month_end_with_end_date() {
+        assert_eq!(
+            lines_after("31.03.2024")[..2],
+            [
+                "invoice AB -> CD 100.00 30.04.2024 Rent",
+                "invoice AB -> CD 100.00 31.05.2024 - 31.12.2024 : monthly Rent",
+            ]
+        );
+        assert_eq!(
+            lines_after("30.09.2024")[0],
+            "invoice AB -> CD 100.00 31.10.2024 - 31.12.2024 : monthly Rent"
+        );
+        // A single remaining occurrence is re-anchored on its own date
+        assert_eq!(
+            lines_after("30.11.2024")[0],
+            "invoice AB -> CD 100.00 31.12.2024 - 31.12.2024 : monthly Rent"
+        );
+        assert!(!lines_after("31.12.2024")
+            .iter()
+            .any(|line| line.ends_with("Rent")));
+    }
+
+    #[test]
+    fn lines_after_open_ended() {
+        let lines = lines_after("31.03.2024");
+        assert_eq!(
+            lines[2..],
+            [
+                "invoice CD -> AB 10.00 30.04.2024 Internet",
+                "invoice CD -> AB 10.00 from 31.05.2024 monthly Internet",
+            ]
+        );
+        // Open-ended invoices keep recurring after the accounting date
+        assert_eq!(
+            lines_after("31.12.2024"),
+            ["invoice CD -> AB 10.00 from 31.01.2025 monthly Internet"]
+        );
+    }
+
+    #[test]
+    fn lines_after_open_ended_with_other_whitespace() {
+        let ledger: Ledger = LEDGER
+            .replace("from 31.01.2024", "from\u{a0}31.01.2024")
+            .parse()
+            .unwrap();
+        assert_eq!(
+            ledger.lines_after("31.03.2024".parse().unwrap())[2..],
+            [
+                "invoice CD -> AB 10.00 30.04.2024 Internet",
+                "invoice CD -> AB 10.00 from\u{a0}31.05.2024 monthly Internet",
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_after_keep_later_entries_as_written() {
+        assert_eq!(
+            lines_after("01.01.2024"),
+            LEDGER.lines().skip(4).collect::<Vec<&str>>()
+        );
+        assert_eq!(
+            lines_after("14.03.2024")[2],
+            "payment AB -> CD 50.00 15.03.2024 Cash"
+        );
+        assert_eq!(lines_after("15.03.2024").len(), 2);
+    }
+
+    #[test]
+    fn errors_of_all_lines() {
+        let text = "accounting_date 31.12.2024
+account AB Anna
+account CD Chloë Müller iban=XX
+
+invoice AB -> XY 10.00 01.02.2024 Food
+payment AB -> CD 5.00 31.02.2024 Cash
+this is not a line
+// comment
+invoice AB : CD -> AB 12.00 32.01.2024 Brot
+";
+        assert_eq!(
+            errors(text),
+            [
+                "3:25-31: invalid IBAN XX",
+                "5:15-16: unknown account XY",
+                "6:23-32: invalid date 31.02.2024",
+                "7:1-18: unrecognized line",
+                "9:29-38: invalid date 32.01.2024",
+            ]
+        );
+    }
+
+    #[test]
+    fn sender_weight_errors() {
+        let text = "accounting_date 31.12.2024
+account AB Anna
+account CD Chloë
+
+invoice AB 60% : CD 30% -> AB 10.00 01.02.2024 Food
+invoice AB 60% : CD*2 -> AB 10.00 01.02.2024 Food
+invoice AB*0 : CD*0 -> AB 10.00 01.02.2024 Food
+invoice AB 40% : CD 60% -> AB 10.00 01.02.2024 Food
+";
+        assert_eq!(
+            errors(text),
+            [
+                "5:9-23: percentages sum up to 90.00%, expected 100%",
+                "6:9-21: either all or no senders need a percentage",
+                "7:9-19: at least one sender needs a weight above zero",
+            ]
+        );
+    }
+
+    #[test]
+    fn attribute_error_after_other_whitespace() {
+        assert_eq!(
+            errors("accounting_date 31.12.2024\naccount AB Anna\u{a0}iban=XX\n"),
+            ["2:17-23: invalid IBAN XX"]
+        );
+    }
+
+    // Amounts owed by each sender for the invoices of the date and note
+    fn invoiced(ledger: &Ledger, date: &str, note: &str) -> Vec<(String, Money)> {
+        let date: Date = date.parse().unwrap();
+        ledger
+            .invoices()
+            .iter()
+            .filter(|invoice| invoice.date() == date && invoice.note() == note)
+            .map(|invoice| {
+                (
+                    ledger.accounts()[invoice.sender_index()].acronym().clone(),
+                    invoice.amount(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn all_split_by_active_days() {
+        let ledger: Ledger = "accounting_date 31.03.2024
+account AB Anna
+account CD Carl active_until=14.02.2024
+account EF Eva active_from=15.02.2024
+
+invoice all -> AB 290.00 from 01.01.2024 monthly Rent
+invoice all -> AB 30.00 14.02.2024 Dinner
+invoice all -> AB 30.00 15.02.2024 Dinner
+"
+        .parse()
+        .unwrap();
+        assert_eq!(
+            invoiced(&ledger, "01.01.2024", "Rent"),
+            [("CD".to_owned(), Money::from_cents(14500))]
+        );
+        // February has 29 days, CD is active for 14 and EF for 15 of them
+        assert_eq!(
+            invoiced(&ledger, "01.02.2024", "Rent"),
+            [
+                ("CD".to_owned(), Money::from_cents(7000)),
+                ("EF".to_owned(), Money::from_cents(7500)),
+            ]
+        );
+        assert_eq!(
+            invoiced(&ledger, "01.03.2024", "Rent"),
+            [("EF".to_owned(), Money::from_cents(14500))]
+        );
+        // An invoice which does not recur is split among the accounts active
+        // on its date
+        assert_eq!(
+            invoiced(&ledger, "14.02.2024", "Dinner"),
+            [("CD".to_owned(), Money::from_cents(1500))]
+        );
+        assert_eq!(
+            invoiced(&ledger, "15.02.2024", "Dinner"),
+            [("EF".to_owned(), Money::from_cents(1500))]
+        );
+    }
+}
@@ -0,0 +1,58 @@
+//! Accounting for shared flats (WG).
+//!
+//! A ledger is a text file starting with an `accounting_date dd.mm.yyyy`
+//! header, followed by `account`, `invoice` and `payment` lines. Parse it
+//! with [`Ledger::new`], [`Ledger::from_reader`] or [`str::parse`], compute
+//! the open amounts with [`Balance::from_ledger`] and write per account
+//! reports with [`report_txt::generate`] or [`report_html::generate`].
+//! [`report_csv`] and [`report_json`] export the same data for spreadsheets
+//! and scripts. [`history`] follows the balance of an account over time
+//! and [`close`] starts a new ledger from the open balances at a cut-off.
+//! Notes may carry a `[category]` and `#tags`, which [`category`] uses to
+//! break down the spending.
+//!
+//! ```
+//! use wg_accounting::{Balance, Ledger};
+//!
+//! let ledger: Ledger = "accounting_date 31.12.2024
+//! account AB Anna
+//! account CD Carl
+//! invoice AB : CD -> CD 10.00 01.12.2024 Groceries
+//! payment AB -> CD 5.00 02.12.2024 Groceries"
+//!     .parse()
+//!     .unwrap();
+//! let balance = Balance::from_ledger(&ledger);
+//! assert!(balance.entries()[0].balance().is_zero());
+//! ```
+
+pub mod account;
+pub mod allocation;
+pub mod balance;
+pub mod category;
+pub mod close;
+pub mod currency;
+pub mod date;
+pub mod error;
+pub mod history;
+pub mod ledger;
+pub mod money;
+pub mod period;
+pub mod qr_bill;
+pub mod recurrence;
+pub mod report;
+pub mod report_csv;
+pub mod report_html;
+pub mod report_json;
+pub mod report_pdf;
+pub mod report_txt;
+pub mod settlement;
+pub mod transaction;
+
+pub use account::Account;
+pub use balance::{Balance, BalanceEntry};
+pub use date::Date;
+pub use error::{LedgerError, ParseError};
+pub use ledger::Ledger;
+pub use money::Money;
+pub use recurrence::Recurrence;
+pub use transaction::Transaction;
@@ -1,36 +1,544 @@
-mod account;
-mod balance;
-mod date;
-mod ledger;
-mod report_txt;
-mod transaction;
+use wg_accounting::{
+    account,
+    category::Breakdown,
+    close, history,
+    period::{Period, PeriodLength},
+    qr_bill, report_csv, report_html, report_json, report_pdf, report_txt, settlement, Balance,
+    Date, Ledger,
+};
 
-use balance::Balance;
-use ledger::Ledger;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-use std::env;
-use std::path::Path;
+#[derive(Parser)]
+#[command(version, about = "Shared flat (WG) accounting")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
 
-fn main() {
-    // Get arguments
-    let args: Vec<String> = env::args().collect();
-    let ledger_path = Path::new(&args[1]);
-    let report_acronyms = &args[2..];
+#[derive(Subcommand)]
+enum Command {
+    /// Generate a report for each given account
+    Report {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        /// Acronyms of the accounts to report on
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        acronyms: Vec<String>,
+        /// Report on all accounts
+        #[arg(long)]
+        all: bool,
+        /// Write the reports of the accounts into one PDF
+        #[arg(long)]
+        combined: bool,
+        /// Write one statement per month, quarter or year instead of one
+        /// report up to the accounting date
+        #[arg(long, value_name = "LENGTH")]
+        period: Option<PeriodLength>,
+        /// Start date of the statements, defaults to the first invoice or
+        /// payment
+        #[arg(long, value_name = "DATE")]
+        from: Option<Date>,
+        /// End date of the statements, defaults to the accounting date
+        #[arg(long, value_name = "DATE")]
+        to: Option<Date>,
+        /// Only include invoices and payments with this #tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Folder to write the reports to, defaults to the folder of the ledger
+        /// or the current folder when reading from stdin
+        #[arg(long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+        #[arg(long, value_enum, default_value_t = Format::Txt)]
+        format: Format,
+    },
+    /// Print the open balance between each pair of accounts
+    Balance {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        #[arg(long, value_enum, default_value_t = Format::Txt)]
+        format: Format,
+    },
+    /// Print the running net balance of an account after every invoice and
+    /// payment, or only between two accounts
+    History {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        /// Acronym of the account
+        acronym: String,
+        /// Acronym of another account to only show the balance between both
+        other_acronym: Option<String>,
+    },
+    /// Print the spending per [category] of an account or the household
+    Categories {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        /// Acronym of the account, defaults to the whole household
+        acronym: Option<String>,
+        /// Start date, defaults to the first invoice or payment
+        #[arg(long, value_name = "DATE")]
+        from: Option<Date>,
+        /// End date, defaults to the accounting date
+        #[arg(long, value_name = "DATE")]
+        to: Option<Date>,
+        /// Only include invoices with this #tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// Check the ledger and report all problems, fails on errors and warnings
+    Check {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+    },
+    /// Print the transfers which settle all open balances
+    Settle {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        #[arg(long, value_enum, default_value_t = Format::Txt)]
+        format: Format,
+    },
+    /// Write a Swiss QR-bill for every open amount the given accounts owe
+    QrBill {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        /// Acronyms of the paying accounts
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        acronyms: Vec<String>,
+        /// Write QR-bills for all accounts
+        #[arg(long)]
+        all: bool,
+        /// Folder to write the QR-bills to, defaults to the folder of the
+        /// ledger or the current folder when reading from stdin
+        #[arg(long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+    },
+    /// Write a new ledger starting with the open balances at the cut-off
+    /// date and the entries which take effect after it
+    Close {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        /// Date up to which invoices and payments are carried over as opening
+        /// balances
+        #[arg(long, value_name = "DATE")]
+        cut_off: Date,
+        /// File to write the closed ledger to, defaults to stdout
+        #[arg(long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Print the parsed ledger
+    Print {
+        #[command(flatten)]
+        ledger: LedgerArgs,
+        #[arg(long, value_enum, default_value_t = Format::Txt)]
+        format: Format,
+    },
+}
 
-    let ledger = Ledger::new(ledger_path);
-    // print!("{}", ledger);
+#[derive(Args)]
+struct LedgerArgs {
+    /// Path to the ledger file, - to read it from stdin
+    #[arg(value_name = "LEDGER")]
+    path: PathBuf,
+    /// Evaluate the ledger at this date (dd.mm.yyyy) instead of its accounting date
+    #[arg(long, value_name = "DATE")]
+    as_of: Option<Date>,
+}
+impl LedgerArgs {
+    fn load(&self) -> Result<Ledger, String> {
+        let ledger = if self.is_stdin() {
+            let stdin = io::stdin().lock();
+            match self.as_of {
+                Some(as_of) => Ledger::from_reader_as_of(stdin, as_of),
+                None => Ledger::from_reader(stdin),
+            }
+            .map_err(|error| error.with_path(Path::new("<stdin>")))
+        } else {
+            match self.as_of {
+                Some(as_of) => Ledger::new_as_of(&self.path, as_of),
+                None => Ledger::new(&self.path),
+            }
+        };
+        let ledger = ledger.map_err(|error| error.to_string().trim_end().to_owned())?;
+        for warning in ledger.warnings() {
+            eprintln!("{}:{}", self.name(), warning);
+        }
+        Ok(ledger)
+    }
+    fn output_folder<'a>(&'a self, output_dir: &'a Option<PathBuf>) -> &'a Path {
+        match output_dir {
+            Some(output_dir) => output_dir.as_path(),
+            None if self.is_stdin() => Path::new("."),
+            None => self.path.parent().unwrap_or(Path::new(".")),
+        }
+    }
+    fn is_stdin(&self) -> bool {
+        self.path.as_os_str() == "-"
+    }
+    fn name(&self) -> String {
+        if self.is_stdin() {
+            "<stdin>".to_owned()
+        } else {
+            self.path.display().to_string()
+        }
+    }
+}
 
-    let mut balance = Balance::new();
-    for invoice in ledger.invoices() {
-        balance.add_invoice(invoice);
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Plain text
+    Txt,
+    /// Self-contained HTML page
+    Html,
+    /// PDF document
+    Pdf,
+    /// Comma separated values
+    Csv,
+    /// JSON, see the report_json module for the schema
+    Json,
+}
+impl Format {
+    fn unsupported(&self, command: &str) -> String {
+        format!(
+            "Format {} is not supported by {}",
+            self.to_possible_value().unwrap().get_name(),
+            command
+        )
     }
-    for payment in ledger.payments() {
-        balance.add_payment(payment);
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
     }
-    // print!("\n{}", balance.to_string(ledger.accounts()));
+}
+
+// Acronyms of all accounts or the given ones, which have to exist
+fn select_acronyms(
+    ledger: &Ledger,
+    acronyms: Vec<String>,
+    all: bool,
+) -> Result<Vec<String>, String> {
+    if all {
+        return Ok(ledger
+            .accounts()
+            .iter()
+            .map(|account| account.acronym().clone())
+            .collect());
+    }
+    for acronym in &acronyms {
+        if account::find_index(acronym, ledger.accounts()).is_none() {
+            return Err(format!("Unknown account {}", acronym));
+        }
+    }
+    Ok(acronyms)
+}
+
+// Periods from the given start or the first invoice or payment up to the
+// given end or the accounting date
+fn select_periods(
+    ledger: &Ledger,
+    length: Option<PeriodLength>,
+    from: Option<Date>,
+    to: Option<Date>,
+) -> Result<Vec<Period>, String> {
+    let to = to.unwrap_or(*ledger.accounting_date());
+    let from = from.unwrap_or_else(|| {
+        ledger
+            .invoices()
+            .iter()
+            .chain(ledger.payments())
+            .map(|transaction| transaction.date())
+            .min()
+            .map_or(to, |first| first.min(to))
+    });
+    if to < from {
+        return Err(format!("End date {} is before start date {}", to, from));
+    }
+    if to > *ledger.accounting_date() {
+        return Err(format!(
+            "End date {} is after the accounting date {}, use --as-of to evaluate the ledger later",
+            to,
+            ledger.accounting_date()
+        ));
+    }
+    match length {
+        Some(length) => Ok(length.periods(from, to)),
+        None => Ok(vec![Period::new(from, to).unwrap()]),
+    }
+}
 
-    let output_folder_path = ledger_path.parent().unwrap();
-    for report_acronym in report_acronyms {
-        report_txt::generate(&ledger, &balance, &output_folder_path, report_acronym);
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Report {
+            ledger: ledger_args,
+            acronyms,
+            all,
+            combined,
+            period,
+            from,
+            to,
+            tag,
+            output_dir,
+            format,
+        } => {
+            let mut ledger = ledger_args.load()?;
+            if let Some(tag) = &tag {
+                ledger.retain_tag(tag.trim_start_matches('#'));
+            }
+            let balance = Balance::from_ledger(&ledger);
+            let report_acronyms = select_acronyms(&ledger, acronyms, all)?;
+            let output_folder_path = ledger_args.output_folder(&output_dir);
+            if combined && format != Format::Pdf {
+                return Err("--combined is only supported with --format pdf".to_owned());
+            }
+            if period.is_some() || from.is_some() || to.is_some() {
+                if format != Format::Txt {
+                    return Err("Period statements are only supported with --format txt".to_owned());
+                }
+                for period in select_periods(&ledger, period, from, to)? {
+                    for report_acronym in &report_acronyms {
+                        report_txt::generate_period(
+                            &ledger,
+                            output_folder_path,
+                            report_acronym,
+                            period,
+                        )
+                        .map_err(|error| {
+                            format!(
+                                "Cannot write statement {} for {}: {}",
+                                period, report_acronym, error
+                            )
+                        })?;
+                    }
+                }
+                return Ok(());
+            }
+            match format {
+                Format::Txt => {
+                    for report_acronym in &report_acronyms {
+                        report_txt::generate(&ledger, &balance, output_folder_path, report_acronym)
+                            .map_err(|error| {
+                                format!("Cannot write report for {}: {}", report_acronym, error)
+                            })?;
+                    }
+                }
+                Format::Html => {
+                    for report_acronym in &report_acronyms {
+                        report_html::generate(
+                            &ledger,
+                            &balance,
+                            output_folder_path,
+                            report_acronym,
+                        )
+                        .map_err(|error| {
+                            format!("Cannot write report for {}: {}", report_acronym, error)
+                        })?;
+                    }
+                }
+                Format::Pdf if combined => {
+                    report_pdf::generate_combined(
+                        &ledger,
+                        &balance,
+                        output_folder_path,
+                        &report_acronyms,
+                    )
+                    .map_err(|error| format!("Cannot write PDF report: {}", error))?;
+                }
+                Format::Pdf => {
+                    for report_acronym in &report_acronyms {
+                        report_pdf::generate(&ledger, &balance, output_folder_path, report_acronym)
+                            .map_err(|error| {
+                                format!("Cannot write report for {}: {}", report_acronym, error)
+                            })?;
+                    }
+                }
+                Format::Json => {
+                    for report_acronym in &report_acronyms {
+                        report_json::generate(
+                            &ledger,
+                            &balance,
+                            output_folder_path,
+                            report_acronym,
+                        )
+                        .map_err(|error| {
+                            format!("Cannot write report for {}: {}", report_acronym, error)
+                        })?;
+                    }
+                }
+                Format::Csv => {
+                    // The CSV export always covers the whole household
+                    if !all {
+                        return Err("CSV reports cover all accounts, use --all".to_owned());
+                    }
+                    report_csv::generate(&ledger, &balance, output_folder_path)
+                        .map_err(|error| format!("Cannot write CSV report: {}", error))?;
+                }
+            }
+        }
+        Command::Balance {
+            ledger: ledger_args,
+            format,
+        } => {
+            let ledger = ledger_args.load()?;
+            let balance = Balance::from_ledger(&ledger);
+            match format {
+                Format::Txt => print!(
+                    "{}",
+                    balance.to_string(ledger.accounts(), ledger.currency())
+                ),
+                Format::Json => report_json::write_balance(&ledger, &balance, &mut io::stdout())
+                    .map_err(|error| format!("Cannot write JSON: {}", error))?,
+                _ => return Err(format.unsupported("balance")),
+            }
+        }
+        Command::History {
+            ledger: ledger_args,
+            acronym,
+            other_acronym,
+        } => {
+            let ledger = ledger_args.load()?;
+            let find_index = |acronym: &String| {
+                account::find_index(acronym, ledger.accounts())
+                    .ok_or_else(|| format!("Unknown account {}", acronym))
+            };
+            let account_index = find_index(&acronym)?;
+            let entries = match &other_acronym {
+                Some(other_acronym) => {
+                    let other_index = find_index(other_acronym)?;
+                    if other_index == account_index {
+                        return Err("History between an account and itself".to_owned());
+                    }
+                    history::pair_history(&ledger, account_index, other_index)
+                }
+                None => history::account_history(&ledger, account_index),
+            };
+            for entry in entries {
+                println!("{}", entry.to_string(ledger.accounts(), ledger.currency()));
+            }
+        }
+        Command::Categories {
+            ledger: ledger_args,
+            acronym,
+            from,
+            to,
+            tag,
+        } => {
+            let mut ledger = ledger_args.load()?;
+            if let Some(tag) = &tag {
+                ledger.retain_tag(tag.trim_start_matches('#'));
+            }
+            let account_index = match &acronym {
+                Some(acronym) => Some(
+                    account::find_index(acronym, ledger.accounts())
+                        .ok_or_else(|| format!("Unknown account {}", acronym))?,
+                ),
+                None => None,
+            };
+            let period = select_periods(&ledger, None, from, to)?[0];
+            print!(
+                "{}",
+                Breakdown::new(&ledger, account_index, period)
+                    .unwrap()
+                    .to_string(ledger.currency())
+            );
+        }
+        Command::Check {
+            ledger: ledger_args,
+        } => {
+            let ledger = ledger_args.load()?;
+            println!(
+                "{}: {} accounts, {} invoices, {} payments",
+                ledger_args.name(),
+                ledger.accounts().len(),
+                ledger.invoices().len(),
+                ledger.payments().len()
+            );
+            if !ledger.warnings().is_empty() {
+                return Err(format!(
+                    "{}: {} warnings",
+                    ledger_args.name(),
+                    ledger.warnings().len()
+                ));
+            }
+        }
+        Command::Settle {
+            ledger: ledger_args,
+            format,
+        } => {
+            let ledger = ledger_args.load()?;
+            let balance = Balance::from_ledger(&ledger);
+            match format {
+                Format::Txt => {
+                    for transfer in settlement::settle(&balance, ledger.accounts().len()) {
+                        println!(
+                            "{}",
+                            transfer.to_string(ledger.accounts(), ledger.currency())
+                        );
+                    }
+                }
+                Format::Json => report_json::write_settlement(&ledger, &balance, &mut io::stdout())
+                    .map_err(|error| format!("Cannot write JSON: {}", error))?,
+                _ => return Err(format.unsupported("settle")),
+            }
+        }
+        Command::QrBill {
+            ledger: ledger_args,
+            acronyms,
+            all,
+            output_dir,
+        } => {
+            let ledger = ledger_args.load()?;
+            let balance = Balance::from_ledger(&ledger);
+            let output_folder_path = ledger_args.output_folder(&output_dir);
+            for acronym in select_acronyms(&ledger, acronyms, all)? {
+                let problems = qr_bill::generate(&ledger, &balance, output_folder_path, &acronym)
+                    .map_err(|error| {
+                    format!("Cannot write QR-bill for {}: {}", acronym, error)
+                })?;
+                for problem in problems {
+                    eprintln!("{}", problem);
+                }
+            }
+        }
+        Command::Close {
+            ledger: ledger_args,
+            cut_off,
+            output,
+        } => {
+            let ledger = ledger_args.load()?;
+            let text = close::close(&ledger, cut_off)
+                .map_err(|reason| format!("Cannot close {}: {}", ledger_args.name(), reason))?;
+            match output {
+                Some(output) => fs::write(&output, text)
+                    .map_err(|error| format!("Cannot write {}: {}", output.display(), error))?,
+                None => print!("{}", text),
+            }
+        }
+        Command::Print {
+            ledger: ledger_args,
+            format,
+        } => {
+            let ledger = ledger_args.load()?;
+            match format {
+                Format::Txt => print!("{}", ledger),
+                Format::Csv => {
+                    report_csv::write(&ledger, &Balance::from_ledger(&ledger), &mut io::stdout())
+                        .map_err(|error| format!("Cannot write CSV: {}", error))?
+                }
+                Format::Json => report_json::write_ledger(&ledger, &mut io::stdout())
+                    .map_err(|error| format!("Cannot write JSON: {}", error))?,
+                _ => return Err(format.unsupported("print")),
+            }
+        }
     }
+    Ok(())
 }
@@ -0,0 +1,136 @@
+use std::fmt;
+use std::iter;
+use std::ops;
+use std::str::FromStr;
+
+/// Amount of money in cents (Rappen).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money {
+    cents: i64,
+}
+impl Money {
+    pub const ZERO: Money = Money { cents: 0 };
+    /// Largest amount accepted when parsing, so that sums of many amounts
+    /// still fit.
+    pub const MAX: Money = Money {
+        cents: 100_000_000_000_000,
+    };
+
+    pub fn from_cents(cents: i64) -> Self {
+        Money { cents }
+    }
+    pub fn cents(&self) -> i64 {
+        self.cents
+    }
+    pub fn is_zero(&self) -> bool {
+        self.cents == 0
+    }
+    pub fn is_negative(&self) -> bool {
+        self.cents < 0
+    }
+    pub fn abs(&self) -> Self {
+        Money {
+            cents: self.cents.abs(),
+        }
+    }
+    pub fn checked_add(self, other: Money) -> Option<Self> {
+        Some(Money {
+            cents: self.cents.checked_add(other.cents)?,
+        })
+    }
+    pub fn checked_sub(self, other: Money) -> Option<Self> {
+        Some(Money {
+            cents: self.cents.checked_sub(other.cents)?,
+        })
+    }
+    pub fn checked_neg(self) -> Option<Self> {
+        Some(Money {
+            cents: self.cents.checked_neg()?,
+        })
+    }
+}
+impl FromStr for Money {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match string.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, string),
+        };
+        let (units, fraction) = match digits.split_once('.') {
+            Some((units, fraction)) => (units, fraction),
+            None => (digits, ""),
+        };
+        if units.is_empty()
+            || !units.chars().all(|c| c.is_ascii_digit())
+            || !fraction.chars().all(|c| c.is_ascii_digit())
+        {
+            return Err(format!("invalid amount {}", string));
+        }
+        if fraction.len() > 2 {
+            return Err(format!(
+                "invalid amount {}: more than two decimal places",
+                string
+            ));
+        }
+        let overflow = || format!("invalid amount {}: too large", string);
+        let mut cents = units
+            .parse::<i64>()
+            .map_err(|_| overflow())?
+            .checked_mul(100)
+            .ok_or_else(overflow)?;
+        if !fraction.is_empty() {
+            let fraction_cents = format!("{:0<2}", fraction).parse::<i64>().unwrap();
+            cents = cents.checked_add(fraction_cents).ok_or_else(overflow)?;
+        }
+        if cents > Money::MAX.cents {
+            return Err(overflow());
+        }
+        Ok(Money {
+            cents: if negative { -cents } else { cents },
+        })
+    }
+}
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        let abs_cents = self.cents.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs_cents / 100, abs_cents % 100)
+    }
+}
+impl ops::Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        self.checked_add(other).expect("money overflow")
+    }
+}
+impl ops::Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        self.checked_sub(other).expect("money overflow")
+    }
+}
+impl ops::Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        self.checked_neg().expect("money overflow")
+    }
+}
+impl ops::AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        *self = *self + other;
+    }
+}
+impl ops::SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        *self = *self - other;
+    }
+}
+impl iter::Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, |sum, amount| sum + amount)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::date::Date;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of the periods of period statements, aligned to the calendar.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PeriodLength {
+    Month,
+    Quarter,
+    Year,
+}
+impl PeriodLength {
+    fn months(&self) -> u32 {
+        match self {
+            PeriodLength::Month => 1,
+            PeriodLength::Quarter => 3,
+            PeriodLength::Year => 12,
+        }
+    }
+    /// Consecutive periods covering `first` up to and including `last`. The
+    /// first and the last period are cut at these dates.
+    pub fn periods(&self, first: Date, last: Date) -> Vec<Period> {
+        let months = self.months();
+        let mut periods: Vec<Period> = Vec::new();
+        let mut start =
+            Date::from_ymd(first.year(), (first.month() - 1) / months * months + 1, 1).unwrap();
+        while start <= last {
+            let next_start = start.checked_add(0, months, 0);
+            let end = next_start
+                .and_then(|next_start| next_start.previous_day())
+                .map_or(last, |end| end.min(last));
+            periods.push(Period {
+                start: start.max(first),
+                end,
+            });
+            match next_start {
+                Some(next_start) => start = next_start,
+                None => break,
+            }
+        }
+        periods
+    }
+}
+impl FromStr for PeriodLength {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match string {
+            "month" => Ok(PeriodLength::Month),
+            "quarter" => Ok(PeriodLength::Quarter),
+            "year" => Ok(PeriodLength::Year),
+            _ => Err(format!(
+                "invalid period {}, expected month, quarter or year",
+                string
+            )),
+        }
+    }
+}
+impl fmt::Display for PeriodLength {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PeriodLength::Month => write!(f, "month"),
+            PeriodLength::Quarter => write!(f, "quarter"),
+            PeriodLength::Year => write!(f, "year"),
+        }
+    }
+}
+
+/// Range of dates from `start` up to and including `end`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Period {
+    start: Date,
+    end: Date,
+}
+impl Period {
+    /// Returns `None` if `end` is before `start`.
+    pub fn new(start: Date, end: Date) -> Option<Self> {
+        if end < start {
+            None
+        } else {
+            Some(Period { start, end })
+        }
+    }
+    pub fn start(&self) -> Date {
+        self.start
+    }
+    pub fn end(&self) -> Date {
+        self.end
+    }
+    pub fn contains(&self, date: Date) -> bool {
+        self.start <= date && date <= self.end
+    }
+}
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {}", self.start, self.end)
+    }
+}
@@ -0,0 +1,483 @@
+//! Swiss QR-bill payment parts for open amounts.
+//!
+//! The creditor needs an `iban` and an `address` attribute on its `account`
+//! line. The payment part is written as SVG with the QR code in the middle
+//! and the payment details next to it, so it can be printed or scanned from
+//! the screen with a banking app.
+
+use crate::account::{self, Account, Address};
+use crate::balance::Balance;
+use crate::ledger::Ledger;
+use crate::money::Money;
+use crate::report::Report;
+
+use qrcode::{Color, EcLevel, QrCode};
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::Path;
+
+// Headings of the payment part per language: title, account, additional
+// information, payer, currency and amount
+const HEADINGS: [(&str, [&str; 6]); 4] = [
+    (
+        "de",
+        [
+            "Zahlteil",
+            "Konto / Zahlbar an",
+            "Zusätzliche Informationen",
+            "Zahlbar durch",
+            "Währung",
+            "Betrag",
+        ],
+    ),
+    (
+        "en",
+        [
+            "Payment part",
+            "Account / Payable to",
+            "Additional information",
+            "Payable by",
+            "Currency",
+            "Amount",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "Section paiement",
+            "Compte / Payable à",
+            "Informations supplémentaires",
+            "Payable par",
+            "Monnaie",
+            "Montant",
+        ],
+    ),
+    (
+        "it",
+        [
+            "Sezione pagamento",
+            "Conto / Pagabile a",
+            "Informazioni supplementari",
+            "Pagabile da",
+            "Valuta",
+            "Importo",
+        ],
+    ),
+];
+
+// Maximum length of the unstructured message
+const MESSAGE_LENGTH: usize = 140;
+const MAX_CENTS: i64 = 99_999_999_999;
+
+// Maximum lengths of the address fields in the payload
+const NAME_LENGTH: usize = 70;
+const STREET_LENGTH: usize = 70;
+const POSTAL_CODE_LENGTH: usize = 16;
+const TOWN_LENGTH: usize = 35;
+
+// Institution identifications of QR-IBANs, which need a QR reference
+const QR_IIDS: std::ops::RangeInclusive<u32> = 30000..=31999;
+
+/// Payment of an open amount from a debtor to a creditor by QR-bill.
+pub struct QrBill<'a> {
+    creditor: &'a Account,
+    debtor: &'a Account,
+    amount: Money,
+    currency: String,
+    message: String,
+}
+impl<'a> QrBill<'a> {
+    /// Checks that the creditor has a Swiss or Liechtenstein IBAN which is
+    /// not a QR-IBAN and an address, that the names and addresses fit into
+    /// the payload and that the amount can be paid in CHF or EUR.
+    pub fn new(
+        creditor: &'a Account,
+        debtor: &'a Account,
+        amount: Money,
+        currency: &str,
+        message: &str,
+    ) -> Result<Self, String> {
+        match creditor.iban() {
+            None => return Err(format!("{} has no IBAN", creditor.acronym())),
+            Some(iban) if !iban.starts_with("CH") && !iban.starts_with("LI") => {
+                return Err(format!(
+                    "IBAN of {} is not a Swiss or Liechtenstein IBAN",
+                    creditor.acronym()
+                ))
+            }
+            Some(iban)
+                if iban
+                    .get(4..9)
+                    .and_then(|iid| iid.parse::<u32>().ok())
+                    .is_some_and(|iid| QR_IIDS.contains(&iid)) =>
+            {
+                return Err(format!(
+                    "IBAN of {} is a QR-IBAN, which needs a QR reference",
+                    creditor.acronym()
+                ))
+            }
+            _ => (),
+        }
+        if creditor.address().is_none() {
+            return Err(format!("{} has no address", creditor.acronym()));
+        }
+        check_lengths(creditor)?;
+        if debtor.address().is_some() {
+            check_lengths(debtor)?;
+        }
+        if currency != "CHF" && currency != "EUR" {
+            return Err(format!(
+                "QR-bills only support CHF and EUR, not {}",
+                currency
+            ));
+        }
+        if amount.is_negative() || amount.is_zero() || amount.cents() > MAX_CENTS {
+            return Err(format!("amount {} cannot be paid by QR-bill", amount));
+        }
+        Ok(QrBill {
+            creditor,
+            debtor,
+            amount,
+            currency: currency.to_owned(),
+            message: message.chars().take(MESSAGE_LENGTH).collect(),
+        })
+    }
+    /// Swiss Payments Code payload of the QR code, version 2.0 without a
+    /// reference.
+    pub fn payload(&self) -> String {
+        let mut lines: Vec<String> = vec![
+            "SPC".to_owned(),
+            "0200".to_owned(),
+            "1".to_owned(),
+            self.creditor.iban().unwrap().clone(),
+        ];
+        add_address(&mut lines, self.creditor.name(), self.creditor.address());
+        // Ultimate creditor, reserved for future use
+        lines.extend(iter::repeat_n(String::new(), 7));
+        lines.push(self.amount.to_string());
+        lines.push(self.currency.clone());
+        if self.debtor.address().is_some() {
+            add_address(&mut lines, self.debtor.name(), self.debtor.address());
+        } else {
+            lines.extend(iter::repeat_n(String::new(), 7));
+        }
+        lines.push("NON".to_owned());
+        lines.push(String::new());
+        lines.push(self.message.clone());
+        lines.push("EPD".to_owned());
+        lines.join("\n")
+    }
+    /// Payment part of 148 x 105 mm as SVG, in the language of the debtor
+    /// or in German.
+    pub fn to_svg(&self) -> String {
+        let language = self.debtor.language().map_or("de", String::as_str);
+        let [title, account_heading, information_heading, payer_heading, currency_heading, amount_heading] =
+            HEADINGS
+                .iter()
+                .find(|(code, _)| *code == language)
+                .unwrap_or(&HEADINGS[0])
+                .1;
+        let code = QrCode::with_error_correction_level(self.payload(), EcLevel::M).unwrap();
+        let width = code.width();
+        let module = 46.0 / width as f32;
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"148mm\" height=\"105mm\" viewBox=\"0 0 148 105\" font-family=\"Helvetica, Arial, sans-serif\">"
+        )
+        .unwrap();
+        writeln!(svg, "<rect width=\"148\" height=\"105\" fill=\"white\"/>").unwrap();
+        add_text(&mut svg, 5.0, 10.0, 3.9, true, title);
+
+        // QR code with the Swiss cross in the middle
+        let mut path = String::new();
+        for (index, color) in code.to_colors().into_iter().enumerate() {
+            if color == Color::Dark {
+                let x = 5.0 + (index % width) as f32 * module;
+                let y = 17.0 + (index / width) as f32 * module;
+                write!(
+                    path,
+                    "M{:.3} {:.3}h{:.3}v{:.3}h-{:.3}z",
+                    x, y, module, module, module
+                )
+                .unwrap();
+            }
+        }
+        writeln!(svg, "<path d=\"{}\" fill=\"black\"/>", path).unwrap();
+        writeln!(
+            svg,
+            "<rect x=\"24.5\" y=\"36.5\" width=\"7\" height=\"7\" fill=\"white\"/>"
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<rect x=\"25\" y=\"37\" width=\"6\" height=\"6\" fill=\"black\"/>"
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<path d=\"M27.4 38.1h1.2v1.3h1.3v1.2h-1.3v1.3h-1.2v-1.3h-1.3v-1.2h1.3z\" fill=\"white\"/>"
+        )
+        .unwrap();
+
+        add_text(&mut svg, 5.0, 71.0, 2.8, true, currency_heading);
+        add_text(&mut svg, 5.0, 75.5, 3.5, false, &self.currency);
+        add_text(&mut svg, 22.0, 71.0, 2.8, true, amount_heading);
+        add_text(
+            &mut svg,
+            22.0,
+            75.5,
+            3.5,
+            false,
+            &format_amount(self.amount),
+        );
+
+        let mut y = 10.0;
+        add_text(&mut svg, 56.0, y, 2.8, true, account_heading);
+        y += 4.0;
+        add_text(
+            &mut svg,
+            56.0,
+            y,
+            3.5,
+            false,
+            &format_iban(self.creditor.iban().unwrap()),
+        );
+        y = add_address_lines(&mut svg, y, self.creditor.name(), self.creditor.address());
+        y += 5.0;
+        add_text(&mut svg, 56.0, y, 2.8, true, information_heading);
+        y += 4.0;
+        add_text(&mut svg, 56.0, y, 3.5, false, &self.message);
+        y += 9.0;
+        add_text(&mut svg, 56.0, y, 2.8, true, payer_heading);
+        if self.debtor.address().is_some() {
+            add_address_lines(&mut svg, y, self.debtor.name(), self.debtor.address());
+        }
+        writeln!(svg, "</svg>").unwrap();
+        svg
+    }
+}
+
+// Name and address fields of the payload have a maximum length
+fn check_lengths(account: &Account) -> Result<(), String> {
+    let address = account.address().unwrap();
+    let fields = [
+        ("name", account.name(), NAME_LENGTH),
+        ("street", address.street(), STREET_LENGTH),
+        ("postal code", address.postal_code(), POSTAL_CODE_LENGTH),
+        ("town", address.town(), TOWN_LENGTH),
+    ];
+    for (field, value, length) in fields {
+        if value.chars().count() > length {
+            return Err(format!(
+                "{} of {} is longer than {} characters",
+                field,
+                account.acronym(),
+                length
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn add_address(lines: &mut Vec<String>, name: &str, address: Option<&Address>) {
+    let address = address.unwrap();
+    lines.push("S".to_owned());
+    lines.push(name.to_owned());
+    lines.push(address.street().clone());
+    // The building number is part of the street
+    lines.push(String::new());
+    lines.push(address.postal_code().clone());
+    lines.push(address.town().clone());
+    lines.push(address.country().clone());
+}
+
+fn add_address_lines(svg: &mut String, mut y: f32, name: &str, address: Option<&Address>) -> f32 {
+    y += 4.0;
+    add_text(svg, 56.0, y, 3.5, false, name);
+    if let Some(address) = address {
+        y += 4.0;
+        add_text(svg, 56.0, y, 3.5, false, address.street());
+        y += 4.0;
+        let place = if address.country() == "CH" {
+            format!("{} {}", address.postal_code(), address.town())
+        } else {
+            format!(
+                "{}-{} {}",
+                address.country(),
+                address.postal_code(),
+                address.town()
+            )
+        };
+        add_text(svg, 56.0, y, 3.5, false, &place);
+    }
+    y
+}
+
+fn add_text(svg: &mut String, x: f32, y: f32, size: f32, bold: bool, text: &str) {
+    let weight = if bold { " font-weight=\"bold\"" } else { "" };
+    writeln!(
+        svg,
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\"{}>{}</text>",
+        x,
+        y,
+        size,
+        weight,
+        escape(text)
+    )
+    .unwrap();
+}
+
+// Amounts are written with a space as thousands separator
+fn format_amount(amount: Money) -> String {
+    let string = amount.to_string();
+    let (units, cents) = string.split_once('.').unwrap();
+    let mut grouped = String::new();
+    for (index, digit) in units.chars().enumerate() {
+        if index > 0 && (units.len() - index) % 3 == 0 {
+            grouped.push(' ');
+        }
+        grouped.push(digit);
+    }
+    format!("{}.{}", grouped, cents)
+}
+
+fn format_iban(iban: &str) -> String {
+    iban.chars()
+        .collect::<Vec<char>>()
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Writes a QR-bill for every open amount the account with the given
+/// acronym owes into the `output` folder. The account has to exist in the
+/// ledger. Returns the reasons for open amounts without a QR-bill.
+pub fn generate(
+    ledger: &Ledger,
+    balance: &Balance,
+    output: &Path,
+    acronym: &str,
+) -> io::Result<Vec<String>> {
+    let accounts = ledger.accounts();
+    let account_index = account::find_index(acronym, accounts).unwrap();
+    let report = Report::new(ledger, balance, account_index);
+    let mut problems: Vec<String> = Vec::new();
+    for transfer in report.open() {
+        if transfer.debtor_index() != account_index {
+            continue;
+        }
+        let debtor = &accounts[transfer.debtor_index()];
+        let creditor = &accounts[transfer.creditor_index()];
+        let message = format!(
+            "WG Abrechnung {} {}",
+            ledger.accounting_date(),
+            debtor.name()
+        );
+        match QrBill::new(
+            creditor,
+            debtor,
+            transfer.amount(),
+            ledger.currency(),
+            &message,
+        ) {
+            Ok(qr_bill) => {
+                let title = format!(
+                    "QR-Rechnung {} {} an {}",
+                    ledger.accounting_date(),
+                    debtor.name(),
+                    creditor.name()
+                );
+                fs::write(
+                    output.join(title.replace(".", "_")).with_extension("svg"),
+                    qr_bill.to_svg(),
+                )?;
+            }
+            Err(reason) => problems.push(format!(
+                "No QR-bill for {} -> {}: {}",
+                debtor.acronym(),
+                creditor.acronym(),
+                reason
+            )),
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(creditor_attributes: &str) -> Option<String> {
+        let text = format!(
+            "accounting_date 31.12.2024\naccount AB Anna {}\naccount CD Carl\n",
+            creditor_attributes
+        );
+        let ledger: Ledger = text.parse().unwrap();
+        let accounts = ledger.accounts();
+        QrBill::new(
+            &accounts[0],
+            &accounts[1],
+            Money::from_cents(1000),
+            "CHF",
+            "",
+        )
+        .err()
+    }
+
+    #[test]
+    fn valid_creditor() {
+        assert_eq!(
+            problem("iban=CH9300762011623852957 address=\"Gasse 1, 8000 Zürich\""),
+            None
+        );
+    }
+
+    #[test]
+    fn qr_iban() {
+        assert_eq!(
+            problem("iban=CH4431999123000889012 address=\"Gasse 1, 8000 Zürich\"").unwrap(),
+            "IBAN of AB is a QR-IBAN, which needs a QR reference"
+        );
+    }
+
+    #[test]
+    fn field_lengths() {
+        let street = "G".repeat(STREET_LENGTH) + " 1";
+        assert_eq!(
+            problem(&format!(
+                "iban=CH9300762011623852957 address=\"{}, 8000 Zürich\"",
+                street
+            ))
+            .unwrap(),
+            "street of AB is longer than 70 characters"
+        );
+        let town = "Z".repeat(TOWN_LENGTH + 1);
+        assert_eq!(
+            problem(&format!(
+                "iban=CH9300762011623852957 address=\"Gasse 1, 8000 {}\"",
+                town
+            ))
+            .unwrap(),
+            "town of AB is longer than 35 characters"
+        );
+        let town = "Z".repeat(TOWN_LENGTH);
+        assert_eq!(
+            problem(&format!(
+                "iban=CH9300762011623852957 address=\"Gasse 1, 8000 {}\"",
+                town
+            )),
+            None
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use crate::date::Date;
+
+use regex::Regex;
+use std::fmt;
+use std::str::FromStr;
+
+/// Interval of a recurring invoice.
+///
+/// Written either as a keyword (`daily`, `weekly`, `monthly`, `quarterly`,
+/// `yearly`), as `every <n> days|weeks|months|quarters|years` or, for
+/// compatibility, as a `d.m.yyyy` triple of days, months and years.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Recurrence {
+    days: u32,
+    months: u32,
+    years: u32,
+}
+impl Recurrence {
+    /// Returns `None` for an empty interval, which would never advance.
+    pub fn new(days: u32, months: u32, years: u32) -> Option<Self> {
+        if days == 0 && months == 0 && years == 0 {
+            None
+        } else {
+            Some(Recurrence {
+                days,
+                months,
+                years,
+            })
+        }
+    }
+    pub fn days(&self) -> u32 {
+        self.days
+    }
+    pub fn months(&self) -> u32 {
+        self.months
+    }
+    pub fn years(&self) -> u32 {
+        self.years
+    }
+    /// Date of the given occurrence, counted from `start` as occurrence 0.
+    /// Each date is computed from the start date so that a day which got
+    /// clamped at the end of a short month is restored afterwards.
+    pub fn nth(&self, start: Date, occurrence: u32) -> Option<Date> {
+        start.checked_add(
+            occurrence.checked_mul(self.days)?,
+            occurrence.checked_mul(self.months)?,
+            occurrence.checked_mul(self.years)?,
+        )
+    }
+    /// All dates from `start` up to and including `end`. Returns `None` if
+    /// a date is out of range.
+    pub fn dates(&self, start: Date, end: Date) -> Option<Vec<Date>> {
+        let mut dates: Vec<Date> = Vec::new();
+        for occurrence in 0u32.. {
+            let date = self.nth(start, occurrence)?;
+            if date > end {
+                break;
+            }
+            dates.push(date);
+        }
+        Some(dates)
+    }
+}
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let numeric_regex =
+            Regex::new(r"^(?P<days>\d{1,2})\.(?P<months>\d{1,2})\.(?P<years>\d{4})$").unwrap();
+        let every_regex =
+            Regex::new(r"^every\s(?P<count>\d+)\s(?P<unit>day|week|month|quarter|year)s?$")
+                .unwrap();
+        let recurrence = if let Some(captures) = numeric_regex.captures(string) {
+            let number = |name: &str| captures.name(name).unwrap().as_str().parse::<u32>().ok();
+            number("days")
+                .zip(number("months"))
+                .zip(number("years"))
+                .and_then(|((days, months), years)| Recurrence::new(days, months, years))
+        } else if let Some(captures) = every_regex.captures(string) {
+            captures
+                .name("count")
+                .unwrap()
+                .as_str()
+                .parse::<u32>()
+                .ok()
+                .and_then(|count| match captures.name("unit").unwrap().as_str() {
+                    "day" => Some((count, 0, 0)),
+                    "week" => count.checked_mul(7).map(|days| (days, 0, 0)),
+                    "month" => Some((0, count, 0)),
+                    "quarter" => count.checked_mul(3).map(|months| (0, months, 0)),
+                    _ => Some((0, 0, count)),
+                })
+                .and_then(|(days, months, years)| Recurrence::new(days, months, years))
+        } else {
+            match string {
+                "daily" => Recurrence::new(1, 0, 0),
+                "weekly" => Recurrence::new(7, 0, 0),
+                "monthly" => Recurrence::new(0, 1, 0),
+                "quarterly" => Recurrence::new(0, 3, 0),
+                "yearly" => Recurrence::new(0, 0, 1),
+                _ => return Err(format!("unknown frequency {}", string)),
+            }
+        };
+        recurrence.ok_or_else(|| format!("invalid frequency {}", string))
+    }
+}
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.days, self.months, self.years) {
+            (1, 0, 0) => write!(f, "daily"),
+            (7, 0, 0) => write!(f, "weekly"),
+            (0, 1, 0) => write!(f, "monthly"),
+            (0, 3, 0) => write!(f, "quarterly"),
+            (0, 0, 1) => write!(f, "yearly"),
+            (days, 0, 0) if days % 7 == 0 => write!(f, "every {} weeks", days / 7),
+            (days, 0, 0) => write!(f, "every {} days", days),
+            (0, months, 0) => write!(f, "every {} months", months),
+            (0, 0, years) => write!(f, "every {} years", years),
+            (days, months, years) => write!(f, "{}.{}.{:04}", days, months, years),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(string: &str) -> Date {
+        string.parse().unwrap()
+    }
+
+    #[test]
+    fn monthly_restores_day_after_short_month() {
+        let monthly: Recurrence = "monthly".parse().unwrap();
+        let start = date("31.01.2024");
+        assert_eq!(monthly.nth(start, 0), Some(start));
+        assert_eq!(monthly.nth(start, 1), Some(date("29.02.2024")));
+        assert_eq!(monthly.nth(start, 2), Some(date("31.03.2024")));
+        assert_eq!(monthly.nth(start, 3), Some(date("30.04.2024")));
+        assert_eq!(monthly.nth(start, 4), Some(date("31.05.2024")));
+    }
+
+    #[test]
+    fn yearly_from_leap_day() {
+        let yearly: Recurrence = "yearly".parse().unwrap();
+        assert_eq!(
+            yearly.dates(date("29.02.2024"), date("31.12.2028")),
+            Some(vec![
+                date("29.02.2024"),
+                date("28.02.2025"),
+                date("28.02.2026"),
+                date("28.02.2027"),
+                date("29.02.2028"),
+            ])
+        );
+    }
+
+    #[test]
+    fn dates_include_end() {
+        let weekly: Recurrence = "weekly".parse().unwrap();
+        assert_eq!(
+            weekly.dates(date("01.01.2024"), date("15.01.2024")),
+            Some(vec![
+                date("01.01.2024"),
+                date("08.01.2024"),
+                date("15.01.2024")
+            ])
+        );
+        assert_eq!(
+            weekly.dates(date("01.01.2024"), date("31.12.2023")),
+            Some(Vec::new())
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::balance::Balance;
+use crate::date::Date;
+use crate::history::{self, HistoryEntry};
+use crate::ledger::Ledger;
+use crate::money::Money;
+use crate::period::Period;
+use crate::settlement::{self, Transfer};
+use crate::transaction::Transaction;
+
+/// Contents of the report of one account, shared by all report formats.
+/// Transactions are sorted by date and taken up to the accounting date.
+pub struct Report<'a> {
+    account_index: usize,
+    to_pay: Vec<&'a Transaction>,
+    to_receive: Vec<&'a Transaction>,
+    paid: Vec<&'a Transaction>,
+    received: Vec<&'a Transaction>,
+    open: Vec<Transfer>,
+    settlement: Vec<Transfer>,
+    history: Vec<HistoryEntry<'a>>,
+}
+impl<'a> Report<'a> {
+    pub fn new(ledger: &'a Ledger, balance: &Balance, account_index: usize) -> Self {
+        let accounting_date = *ledger.accounting_date();
+        let [to_pay, to_receive, paid, received] =
+            split_transactions(ledger, account_index, |date| date <= accounting_date);
+
+        let settlement = settlement::settle(balance, ledger.accounts().len())
+            .into_iter()
+            .filter(|transfer| {
+                transfer.debtor_index() == account_index
+                    || transfer.creditor_index() == account_index
+            })
+            .collect();
+
+        Report {
+            account_index,
+            to_pay,
+            to_receive,
+            paid,
+            received,
+            open: open_transfers(balance, account_index),
+            settlement,
+            history: history::account_history(ledger, account_index),
+        }
+    }
+    pub fn account_index(&self) -> usize {
+        self.account_index
+    }
+    /// Invoices the account has to pay.
+    pub fn to_pay(&self) -> &[&'a Transaction] {
+        &self.to_pay
+    }
+    /// Invoices other accounts have to pay to the account.
+    pub fn to_receive(&self) -> &[&'a Transaction] {
+        &self.to_receive
+    }
+    pub fn paid(&self) -> &[&'a Transaction] {
+        &self.paid
+    }
+    pub fn received(&self) -> &[&'a Transaction] {
+        &self.received
+    }
+    /// Open balances with other accounts, from debtor to creditor.
+    pub fn open(&self) -> &[Transfer] {
+        &self.open
+    }
+    /// Transfers of the settlement plan the account takes part in.
+    pub fn settlement(&self) -> &[Transfer] {
+        &self.settlement
+    }
+    /// Invoices and payments of the account with its net balance after each.
+    pub fn history(&self) -> &[HistoryEntry<'a>] {
+        &self.history
+    }
+}
+
+/// Statement of one account for a period: the balances carried in, the
+/// invoices and payments dated in the period and the balances at its end.
+pub struct PeriodReport<'a> {
+    account_index: usize,
+    period: Period,
+    opening: Vec<Transfer>,
+    to_pay: Vec<&'a Transaction>,
+    to_receive: Vec<&'a Transaction>,
+    paid: Vec<&'a Transaction>,
+    received: Vec<&'a Transaction>,
+    closing: Vec<Transfer>,
+}
+impl<'a> PeriodReport<'a> {
+    /// Returns `None` if the period ends after the accounting date.
+    pub fn new(ledger: &'a Ledger, account_index: usize, period: Period) -> Option<Self> {
+        let closing = Balance::from_ledger_as_of(ledger, period.end())?;
+        let opening = match period.start().previous_day() {
+            Some(date) => Balance::from_ledger_as_of(ledger, date)?,
+            None => Balance::new(),
+        };
+        let [to_pay, to_receive, paid, received] =
+            split_transactions(ledger, account_index, |date| period.contains(date));
+        Some(PeriodReport {
+            account_index,
+            period,
+            opening: open_transfers(&opening, account_index),
+            to_pay,
+            to_receive,
+            paid,
+            received,
+            closing: open_transfers(&closing, account_index),
+        })
+    }
+    pub fn account_index(&self) -> usize {
+        self.account_index
+    }
+    pub fn period(&self) -> Period {
+        self.period
+    }
+    /// Open balances with other accounts the day before the period starts.
+    pub fn opening(&self) -> &[Transfer] {
+        &self.opening
+    }
+    pub fn to_pay(&self) -> &[&'a Transaction] {
+        &self.to_pay
+    }
+    pub fn to_receive(&self) -> &[&'a Transaction] {
+        &self.to_receive
+    }
+    pub fn paid(&self) -> &[&'a Transaction] {
+        &self.paid
+    }
+    pub fn received(&self) -> &[&'a Transaction] {
+        &self.received
+    }
+    /// Open balances with other accounts at the end of the period.
+    pub fn closing(&self) -> &[Transfer] {
+        &self.closing
+    }
+}
+
+/// Direction of the transactions in a table of a report, seen from the
+/// account of the report.
+#[derive(Copy, Clone)]
+pub(crate) enum MoneyFlow {
+    In,
+    Out,
+}
+impl MoneyFlow {
+    /// Direction of the transaction from the view of the account, which has
+    /// to be its sender or recipient.
+    pub(crate) fn of(transaction: &Transaction, account_index: usize) -> Self {
+        if transaction.sender_index() == account_index {
+            MoneyFlow::Out
+        } else {
+            MoneyFlow::In
+        }
+    }
+    /// Preposition in front of the other account.
+    pub(crate) fn preposition(&self) -> &'static str {
+        match self {
+            MoneyFlow::In => "Von",
+            MoneyFlow::Out => "An",
+        }
+    }
+    /// Index of the other account of the transaction.
+    pub(crate) fn other_index(&self, transaction: &Transaction) -> usize {
+        match self {
+            MoneyFlow::In => transaction.sender_index(),
+            MoneyFlow::Out => transaction.recipient_index(),
+        }
+    }
+}
+
+/// Formats an amount as in the reports, whole amounts as `12.- CHF`.
+pub(crate) fn format_amount(amount: Money, currency: &str) -> String {
+    let sign = if amount.is_negative() { "-" } else { "" };
+    let units = amount.cents().unsigned_abs() / 100;
+    let cents = amount.cents().unsigned_abs() % 100;
+    if cents > 0 {
+        format!("{}{}.{:02} {}", sign, units, cents, currency)
+    } else {
+        format!("{}{}.- {}", sign, units, currency)
+    }
+}
+
+// Invoices to pay and to receive, payments paid and received of the account
+// with a date accepted by the filter, sorted by date
+fn split_transactions(
+    ledger: &Ledger,
+    account_index: usize,
+    filter: impl Fn(Date) -> bool,
+) -> [Vec<&Transaction>; 4] {
+    let mut to_pay: Vec<&Transaction> = Vec::new();
+    let mut to_receive: Vec<&Transaction> = Vec::new();
+    for invoice in ledger.invoices() {
+        if !filter(invoice.date()) {
+            continue;
+        }
+        if invoice.sender_index() == account_index {
+            to_pay.push(invoice);
+        } else if invoice.recipient_index() == account_index {
+            to_receive.push(invoice);
+        }
+    }
+
+    let mut paid: Vec<&Transaction> = Vec::new();
+    let mut received: Vec<&Transaction> = Vec::new();
+    for payment in ledger.payments() {
+        if !filter(payment.date()) {
+            continue;
+        }
+        if payment.sender_index() == account_index {
+            paid.push(payment);
+        } else if payment.recipient_index() == account_index {
+            received.push(payment);
+        }
+    }
+
+    to_pay.sort();
+    to_receive.sort();
+    paid.sort();
+    received.sort();
+    [to_pay, to_receive, paid, received]
+}
+
+// Nonzero balances of the account, from debtor to creditor
+fn open_transfers(balance: &Balance, account_index: usize) -> Vec<Transfer> {
+    let mut open_entries: Vec<_> = balance
+        .entries()
+        .iter()
+        .filter(|balance_entry| {
+            balance_entry.sender_index() == account_index
+                || balance_entry.recipient_index() == account_index
+        })
+        .collect();
+    open_entries.sort_by_key(|balance_entry| balance_entry.balance());
+    open_entries
+        .into_iter()
+        .filter_map(|balance_entry| balance_entry.debt())
+        .collect()
+}
@@ -0,0 +1,159 @@
+use crate::account::Account;
+use crate::balance::Balance;
+use crate::ledger::Ledger;
+use crate::transaction::Transaction;
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+const HEADER: [&str; 11] = [
+    "date",
+    "from",
+    "from_name",
+    "to",
+    "to_name",
+    "amount",
+    "currency",
+    "original_amount",
+    "original_currency",
+    "note",
+    "kind",
+];
+
+/// Writes all invoices, payments and open balances of the ledger as one CSV
+/// file into the `output` folder.
+pub fn generate(ledger: &Ledger, balance: &Balance, output: &Path) -> io::Result<()> {
+    let title = format!("WG Abrechnung {}", ledger.accounting_date());
+    let mut file = File::create(output.join(title.replace(".", "_")).with_extension("csv"))?;
+    write(ledger, balance, &mut file)
+}
+
+/// Writes the invoices and payments up to the accounting date and the open
+/// balances of the ledger as CSV. Balances are dated at the accounting date
+/// and go from debtor to creditor.
+pub fn write<W: Write>(ledger: &Ledger, balance: &Balance, writer: &mut W) -> io::Result<()> {
+    let accounts = ledger.accounts();
+    let currency = ledger.currency();
+    write_record(writer, &HEADER)?;
+
+    let mut invoices: Vec<&Transaction> =
+        ledger.invoices_as_of(*ledger.accounting_date()).collect();
+    invoices.sort();
+    for invoice in invoices {
+        write_transaction(writer, accounts, currency, invoice, "invoice")?;
+    }
+    let mut payments: Vec<&Transaction> =
+        ledger.payments_as_of(*ledger.accounting_date()).collect();
+    payments.sort();
+    for payment in payments {
+        write_transaction(writer, accounts, currency, payment, "payment")?;
+    }
+    for debt in balance.entries().iter().filter_map(|entry| entry.debt()) {
+        let debtor_index = debt.debtor_index();
+        let creditor_index = debt.creditor_index();
+        write_record(
+            writer,
+            &[
+                &ledger.accounting_date().to_string(),
+                accounts[debtor_index].acronym(),
+                accounts[debtor_index].name(),
+                accounts[creditor_index].acronym(),
+                accounts[creditor_index].name(),
+                &debt.amount().to_string(),
+                currency,
+                "",
+                "",
+                "",
+                "balance",
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn write_transaction<W: Write>(
+    writer: &mut W,
+    accounts: &[Account],
+    currency: &str,
+    transaction: &Transaction,
+    kind: &str,
+) -> io::Result<()> {
+    let sender = &accounts[transaction.sender_index()];
+    let recipient = &accounts[transaction.recipient_index()];
+    let (original_amount, original_currency) = match transaction.original_amount() {
+        Some(original_amount) => (
+            original_amount.amount().to_string(),
+            original_amount.currency().as_str(),
+        ),
+        None => (String::new(), ""),
+    };
+    write_record(
+        writer,
+        &[
+            &transaction.date().to_string(),
+            sender.acronym(),
+            sender.name(),
+            recipient.acronym(),
+            recipient.name(),
+            &transaction.amount().to_string(),
+            currency,
+            &original_amount,
+            original_currency,
+            &transaction.note(),
+            kind,
+        ],
+    )
+}
+
+fn write_record<W: Write>(writer: &mut W, fields: &[&str]) -> io::Result<()> {
+    let line: Vec<String> = fields.iter().map(|field| quote(field)).collect();
+    write!(writer, "{}\r\n", line.join(","))
+}
+
+// Quotes a field as described in RFC 4180 if it contains a separator, a
+// quote or a line break
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_notes() {
+        let ledger: Ledger = "accounting_date 31.12.2024
+account AB Anna
+account CD Chloë
+
+invoice CD -> AB 10.00 01.02.2024 Brot, \"Käse\"
+"
+        .parse()
+        .unwrap();
+        let mut output = Vec::new();
+        write(&ledger, &Balance::from_ledger(&ledger), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let records: Vec<&str> = output.split("\r\n").collect();
+        assert_eq!(
+            records,
+            [
+                "date,from,from_name,to,to_name,amount,currency,original_amount,original_currency,note,kind",
+                "01.02.2024,CD,Chloë,AB,Anna,10.00,CHF,,,\"Brot, \"\"Käse\"\"\",invoice",
+                "31.12.2024,CD,Chloë,AB,Anna,10.00,CHF,,,,balance",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn quote_line_breaks() {
+        let mut output = Vec::new();
+        write_record(&mut output, &["a\r\nb", "c\nd", "e"]).unwrap();
+        assert_eq!(output, b"\"a\r\nb\",\"c\nd\",e\r\n");
+    }
+}
@@ -0,0 +1,224 @@
+use crate::account::{self, Account};
+use crate::balance::Balance;
+use crate::history::HistoryEntry;
+use crate::ledger::Ledger;
+use crate::report::{self, MoneyFlow, Report};
+use crate::settlement::Transfer;
+use crate::transaction::Transaction;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const STYLE: &str = "
+body { font-family: sans-serif; font-size: 11pt; margin: 2em; color: #000; }
+h1 { font-size: 16pt; }
+h2 { font-size: 13pt; margin-top: 1.5em; border-bottom: 1px solid #888; }
+table { border-collapse: collapse; width: 100%; }
+th, td { padding: 0.2em 0.6em; text-align: left; vertical-align: top; }
+th { border-bottom: 1px solid #888; }
+tr:nth-child(even) td { background: #f2f2f2; }
+.amount { text-align: right; white-space: nowrap; font-variant-numeric: tabular-nums; }
+.date { white-space: nowrap; }
+@media print {
+  body { margin: 0; font-size: 10pt; }
+  tr:nth-child(even) td { background: none; }
+  h2 { break-after: avoid; }
+  tr { break-inside: avoid; }
+}
+";
+
+/// Writes the HTML report of the account with the given acronym into the
+/// `output` folder. The file is self-contained and can be printed or sent
+/// by email. The account has to exist in the ledger.
+pub fn generate(
+    ledger: &Ledger,
+    balance: &Balance,
+    output: &Path,
+    acronym: &str,
+) -> io::Result<()> {
+    let account_index = account::find_index(acronym, ledger.accounts()).unwrap();
+    let title = format!(
+        "WG Abrechnung {} {}",
+        ledger.accounting_date(),
+        ledger.accounts()[account_index].name()
+    );
+    let file = File::create(output.join(title.replace(".", "_")).with_extension("html"))?;
+    let mut writer = BufWriter::new(file);
+    write(ledger, balance, account_index, &mut writer)?;
+    writer.flush()
+}
+
+/// Writes the HTML report of the account with the given index.
+pub fn write<W: Write>(
+    ledger: &Ledger,
+    balance: &Balance,
+    account_index: usize,
+    writer: &mut W,
+) -> io::Result<()> {
+    let accounts = ledger.accounts();
+    let currency = ledger.currency();
+    let title = format!(
+        "WG Abrechnung {} {}",
+        ledger.accounting_date(),
+        accounts[account_index].name()
+    );
+    let report = Report::new(ledger, balance, account_index);
+
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html lang=\"de\">")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{}</title>", escape(&title))?;
+    writeln!(writer, "<style>{}</style>", STYLE)?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    writeln!(writer, "<h1>{}</h1>", escape(&title))?;
+    add_transaction_table(
+        writer,
+        "Zu zahlen",
+        MoneyFlow::Out,
+        accounts,
+        currency,
+        report.to_pay(),
+    )?;
+    add_transaction_table(
+        writer,
+        "Zu gute",
+        MoneyFlow::In,
+        accounts,
+        currency,
+        report.to_receive(),
+    )?;
+    add_transaction_table(
+        writer,
+        "Gezahlt",
+        MoneyFlow::Out,
+        accounts,
+        currency,
+        report.paid(),
+    )?;
+    add_transaction_table(
+        writer,
+        "Erhalten",
+        MoneyFlow::In,
+        accounts,
+        currency,
+        report.received(),
+    )?;
+    add_debt_table(writer, "Noch offen", accounts, currency, report.open())?;
+    add_debt_table(writer, "Ausgleich", accounts, currency, report.settlement())?;
+    add_history_table(writer, accounts, currency, account_index, report.history())?;
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")
+}
+
+fn add_transaction_table<W: Write>(
+    writer: &mut W,
+    name: &str,
+    money_flow: MoneyFlow,
+    accounts: &[Account],
+    currency: &str,
+    transactions: &[&Transaction],
+) -> io::Result<()> {
+    if transactions.is_empty() {
+        return Ok(());
+    }
+    let preposition = money_flow.preposition();
+    writeln!(writer, "<h2>{}</h2>", name)?;
+    writeln!(writer, "<table>")?;
+    writeln!(
+        writer,
+        "<tr><th>Datum</th><th>{}</th><th class=\"amount\">Betrag</th><th>Notiz</th></tr>",
+        preposition
+    )?;
+    for transaction in transactions {
+        let other_index = money_flow.other_index(transaction);
+        let mut note = escape(&transaction.note());
+        if let Some(original_amount) = transaction.original_amount() {
+            note.push_str(&format!(
+                " ({})",
+                report::format_amount(original_amount.amount(), original_amount.currency())
+            ));
+        }
+        writeln!(
+            writer,
+            "<tr><td class=\"date\">{}</td><td>{}</td><td class=\"amount\">{}</td><td>{}</td></tr>",
+            transaction.date(),
+            escape(accounts[other_index].name()),
+            report::format_amount(transaction.amount(), currency),
+            note
+        )?;
+    }
+    writeln!(writer, "</table>")
+}
+
+fn add_debt_table<W: Write>(
+    writer: &mut W,
+    name: &str,
+    accounts: &[Account],
+    currency: &str,
+    transfers: &[Transfer],
+) -> io::Result<()> {
+    writeln!(writer, "<h2>{}</h2>", name)?;
+    if transfers.is_empty() {
+        return writeln!(writer, "<p>-</p>");
+    }
+    writeln!(writer, "<table>")?;
+    writeln!(
+        writer,
+        "<tr><th>Von</th><th>An</th><th class=\"amount\">Betrag</th></tr>"
+    )?;
+    for transfer in transfers {
+        writeln!(
+            writer,
+            "<tr><td>{}</td><td>{}</td><td class=\"amount\">{}</td></tr>",
+            escape(accounts[transfer.debtor_index()].name()),
+            escape(accounts[transfer.creditor_index()].name()),
+            report::format_amount(transfer.amount(), currency)
+        )?;
+    }
+    writeln!(writer, "</table>")
+}
+
+fn add_history_table<W: Write>(
+    writer: &mut W,
+    accounts: &[Account],
+    currency: &str,
+    account_index: usize,
+    history: &[HistoryEntry],
+) -> io::Result<()> {
+    if history.is_empty() {
+        return Ok(());
+    }
+    writeln!(writer, "<h2>Verlauf</h2>")?;
+    writeln!(writer, "<table>")?;
+    writeln!(
+        writer,
+        "<tr><th>Datum</th><th>An / Von</th><th class=\"amount\">Betrag</th><th class=\"amount\">Saldo</th><th>Notiz</th></tr>"
+    )?;
+    for entry in history {
+        let transaction = entry.transaction();
+        let money_flow = MoneyFlow::of(transaction, account_index);
+        let preposition = money_flow.preposition();
+        let other_index = money_flow.other_index(transaction);
+        writeln!(
+            writer,
+            "<tr><td class=\"date\">{}</td><td>{} {}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td><td>{}</td></tr>",
+            transaction.date(),
+            preposition,
+            escape(accounts[other_index].name()),
+            report::format_amount(entry.change(), currency),
+            report::format_amount(entry.balance(), currency),
+            escape(&transaction.note())
+        )?;
+    }
+    writeln!(writer, "</table>")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
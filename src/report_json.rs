@@ -0,0 +1,377 @@
+//! JSON export of ledgers, balances, settlements and account reports.
+//!
+//! Every document is an object with a `schema_version` (currently 1), which
+//! is only increased for changes that break existing readers. Dates are
+//! written as `yyyy-mm-dd`, amounts as decimal strings with two decimal
+//! places (`"12.50"`) to avoid rounding, and accounts by their acronym.
+//!
+//! A transaction is written as
+//!
+//! ```text
+//! {"date": "2024-06-15", "from": "AB", "to": "CD", "amount": "31.67",
+//!  "note": "[Rent] Holiday flat #ticino", "category": "Rent",
+//!  "tags": ["ticino"], "original": {"amount": "33.34", "currency": "EUR"}}
+//! ```
+//!
+//! where `category` is `null` without a `[category]` in the note and
+//! `original` is `null` unless the amount was converted from another
+//! currency. Open amounts and transfers are written as
+//! `{"debtor": "AB", "creditor": "CD", "amount": "12.00"}`.
+//!
+//! An account is written with its acronym, name and optional attributes,
+//! which are `null` if not set:
+//!
+//! ```text
+//! {"acronym": "AB", "name": "Anna", "iban": "CH9300762011623852957",
+//!  "address": {"street": "Gasse 1", "postal_code": "8000", "town": "Zürich",
+//!  "country": "CH"}, "email": null, "language": "de",
+//!  "active_from": "2024-04-01", "active_until": null}
+//! ```
+//!
+//! - [`write_ledger`]: `accounting_date`, `currency`, `remainder_policy`,
+//!   `accounts`, `rates` (`from`, `to`, `rate`, `date`) and the expanded
+//!   `invoices` and `payments`.
+//! - [`write_balance`]: `accounting_date`, `currency` and the nonzero
+//!   `balances` between pairs of accounts.
+//! - [`write_settlement`]: `accounting_date`, `currency` and the `transfers`
+//!   which settle all open balances.
+//! - [`write_report`]: `accounting_date`, `currency`, the `account` and the
+//!   sections `to_pay`, `to_receive`, `paid`, `received` (transactions),
+//!   `open` and `settlement` (open amounts) and `history`. A history entry
+//!   has the `kind` (`invoice` or `payment`), the `transaction`, the signed
+//!   `change` of the net balance of the account and its net `balance`
+//!   afterwards, positive if owed to the account.
+
+use crate::account::{self, Account};
+use crate::balance::Balance;
+use crate::currency::ForeignAmount;
+use crate::history::{EntryKind, HistoryEntry};
+use crate::ledger::Ledger;
+use crate::money::Money;
+use crate::report::Report;
+use crate::settlement::{self, Transfer};
+use crate::transaction::Transaction;
+
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct LedgerDocument<'a> {
+    schema_version: u32,
+    accounting_date: String,
+    currency: &'a str,
+    remainder_policy: String,
+    accounts: Vec<AccountObject<'a>>,
+    rates: Vec<RateObject<'a>>,
+    invoices: Vec<TransactionObject<'a>>,
+    payments: Vec<TransactionObject<'a>>,
+}
+
+#[derive(Serialize)]
+struct BalanceDocument<'a> {
+    schema_version: u32,
+    accounting_date: String,
+    currency: &'a str,
+    balances: Vec<DebtObject<'a>>,
+}
+
+#[derive(Serialize)]
+struct SettlementDocument<'a> {
+    schema_version: u32,
+    accounting_date: String,
+    currency: &'a str,
+    transfers: Vec<DebtObject<'a>>,
+}
+
+#[derive(Serialize)]
+struct ReportDocument<'a> {
+    schema_version: u32,
+    accounting_date: String,
+    currency: &'a str,
+    account: AccountObject<'a>,
+    to_pay: Vec<TransactionObject<'a>>,
+    to_receive: Vec<TransactionObject<'a>>,
+    paid: Vec<TransactionObject<'a>>,
+    received: Vec<TransactionObject<'a>>,
+    open: Vec<DebtObject<'a>>,
+    settlement: Vec<DebtObject<'a>>,
+    history: Vec<HistoryObject<'a>>,
+}
+
+#[derive(Serialize)]
+struct AccountObject<'a> {
+    acronym: &'a str,
+    name: &'a str,
+    iban: Option<&'a str>,
+    address: Option<AddressObject<'a>>,
+    email: Option<&'a str>,
+    language: Option<&'a str>,
+    active_from: Option<String>,
+    active_until: Option<String>,
+}
+impl<'a> AccountObject<'a> {
+    fn new(account: &'a Account) -> Self {
+        AccountObject {
+            acronym: account.acronym(),
+            name: account.name(),
+            iban: account.iban().map(String::as_str),
+            address: account.address().map(|address| AddressObject {
+                street: address.street(),
+                postal_code: address.postal_code(),
+                town: address.town(),
+                country: address.country(),
+            }),
+            email: account.email().map(String::as_str),
+            language: account.language().map(String::as_str),
+            active_from: account.active_from().map(|date| date.to_iso_string()),
+            active_until: account.active_until().map(|date| date.to_iso_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AddressObject<'a> {
+    street: &'a str,
+    postal_code: &'a str,
+    town: &'a str,
+    country: &'a str,
+}
+
+#[derive(Serialize)]
+struct RateObject<'a> {
+    from: &'a str,
+    to: &'a str,
+    rate: String,
+    date: String,
+}
+
+#[derive(Serialize)]
+struct TransactionObject<'a> {
+    date: String,
+    from: &'a str,
+    to: &'a str,
+    amount: String,
+    note: String,
+    category: Option<&'a str>,
+    tags: &'a [String],
+    original: Option<OriginalObject<'a>>,
+}
+impl<'a> TransactionObject<'a> {
+    fn new(accounts: &'a [Account], transaction: &'a Transaction) -> Self {
+        TransactionObject {
+            date: transaction.date().to_iso_string(),
+            from: accounts[transaction.sender_index()].acronym(),
+            to: accounts[transaction.recipient_index()].acronym(),
+            amount: transaction.amount().to_string(),
+            note: transaction.note(),
+            category: transaction.category().map(String::as_str),
+            tags: transaction.tags(),
+            original: transaction.original_amount().map(OriginalObject::new),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct HistoryObject<'a> {
+    kind: &'static str,
+    transaction: TransactionObject<'a>,
+    change: String,
+    balance: String,
+}
+impl<'a> HistoryObject<'a> {
+    fn new(accounts: &'a [Account], entry: &HistoryEntry<'a>) -> Self {
+        HistoryObject {
+            kind: match entry.kind() {
+                EntryKind::Invoice => "invoice",
+                EntryKind::Payment => "payment",
+            },
+            transaction: TransactionObject::new(accounts, entry.transaction()),
+            change: entry.change().to_string(),
+            balance: entry.balance().to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OriginalObject<'a> {
+    amount: String,
+    currency: &'a str,
+}
+impl<'a> OriginalObject<'a> {
+    fn new(original_amount: &'a ForeignAmount) -> Self {
+        OriginalObject {
+            amount: original_amount.amount().to_string(),
+            currency: original_amount.currency(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct DebtObject<'a> {
+    debtor: &'a str,
+    creditor: &'a str,
+    amount: String,
+}
+impl<'a> DebtObject<'a> {
+    fn new(
+        accounts: &'a [Account],
+        debtor_index: usize,
+        creditor_index: usize,
+        amount: Money,
+    ) -> Self {
+        DebtObject {
+            debtor: accounts[debtor_index].acronym(),
+            creditor: accounts[creditor_index].acronym(),
+            amount: amount.to_string(),
+        }
+    }
+    fn from_transfer(accounts: &'a [Account], transfer: &Transfer) -> Self {
+        DebtObject::new(
+            accounts,
+            transfer.debtor_index(),
+            transfer.creditor_index(),
+            transfer.amount(),
+        )
+    }
+}
+
+fn transaction_objects<'a>(
+    accounts: &'a [Account],
+    transactions: &[&'a Transaction],
+) -> Vec<TransactionObject<'a>> {
+    transactions
+        .iter()
+        .map(|transaction| TransactionObject::new(accounts, transaction))
+        .collect()
+}
+
+/// Writes the ledger with its expanded invoices and payments.
+pub fn write_ledger<W: Write>(ledger: &Ledger, writer: &mut W) -> io::Result<()> {
+    let accounts = ledger.accounts();
+    let document = LedgerDocument {
+        schema_version: SCHEMA_VERSION,
+        accounting_date: ledger.accounting_date().to_iso_string(),
+        currency: ledger.currency(),
+        remainder_policy: ledger.remainder_policy().to_string(),
+        accounts: accounts.iter().map(AccountObject::new).collect(),
+        rates: ledger
+            .rates()
+            .iter()
+            .map(|rate| RateObject {
+                from: rate.from(),
+                to: rate.to(),
+                rate: rate.rate(),
+                date: rate.date().to_iso_string(),
+            })
+            .collect(),
+        invoices: transaction_objects(accounts, &ledger.invoices().iter().collect::<Vec<_>>()),
+        payments: transaction_objects(accounts, &ledger.payments().iter().collect::<Vec<_>>()),
+    };
+    write_document(&document, writer)
+}
+
+/// Writes the nonzero open amounts between pairs of accounts.
+pub fn write_balance<W: Write>(
+    ledger: &Ledger,
+    balance: &Balance,
+    writer: &mut W,
+) -> io::Result<()> {
+    let accounts = ledger.accounts();
+    let document = BalanceDocument {
+        schema_version: SCHEMA_VERSION,
+        accounting_date: ledger.accounting_date().to_iso_string(),
+        currency: ledger.currency(),
+        balances: balance
+            .entries()
+            .iter()
+            .filter_map(|balance_entry| balance_entry.debt())
+            .map(|debt| DebtObject::from_transfer(accounts, &debt))
+            .collect(),
+    };
+    write_document(&document, writer)
+}
+
+/// Writes the transfers which settle all open balances.
+pub fn write_settlement<W: Write>(
+    ledger: &Ledger,
+    balance: &Balance,
+    writer: &mut W,
+) -> io::Result<()> {
+    let accounts = ledger.accounts();
+    let document = SettlementDocument {
+        schema_version: SCHEMA_VERSION,
+        accounting_date: ledger.accounting_date().to_iso_string(),
+        currency: ledger.currency(),
+        transfers: settlement::settle(balance, accounts.len())
+            .iter()
+            .map(|transfer| DebtObject::from_transfer(accounts, transfer))
+            .collect(),
+    };
+    write_document(&document, writer)
+}
+
+/// Writes the report of the account with the given index.
+pub fn write_report<W: Write>(
+    ledger: &Ledger,
+    balance: &Balance,
+    account_index: usize,
+    writer: &mut W,
+) -> io::Result<()> {
+    let accounts = ledger.accounts();
+    let report = Report::new(ledger, balance, account_index);
+    let document = ReportDocument {
+        schema_version: SCHEMA_VERSION,
+        accounting_date: ledger.accounting_date().to_iso_string(),
+        currency: ledger.currency(),
+        account: AccountObject::new(&accounts[account_index]),
+        to_pay: transaction_objects(accounts, report.to_pay()),
+        to_receive: transaction_objects(accounts, report.to_receive()),
+        paid: transaction_objects(accounts, report.paid()),
+        received: transaction_objects(accounts, report.received()),
+        open: report
+            .open()
+            .iter()
+            .map(|transfer| DebtObject::from_transfer(accounts, transfer))
+            .collect(),
+        settlement: report
+            .settlement()
+            .iter()
+            .map(|transfer| DebtObject::from_transfer(accounts, transfer))
+            .collect(),
+        history: report
+            .history()
+            .iter()
+            .map(|entry| HistoryObject::new(accounts, entry))
+            .collect(),
+    };
+    write_document(&document, writer)
+}
+
+/// Writes the JSON report of the account with the given acronym into the
+/// `output` folder. The account has to exist in the ledger.
+pub fn generate(
+    ledger: &Ledger,
+    balance: &Balance,
+    output: &Path,
+    acronym: &str,
+) -> io::Result<()> {
+    let account_index = account::find_index(acronym, ledger.accounts()).unwrap();
+    let title = format!(
+        "WG Abrechnung {} {}",
+        ledger.accounting_date(),
+        ledger.accounts()[account_index].name()
+    );
+    let file = File::create(output.join(title.replace(".", "_")).with_extension("json"))?;
+    let mut writer = BufWriter::new(file);
+    write_report(ledger, balance, account_index, &mut writer)?;
+    writer.flush()
+}
+
+fn write_document<W: Write, T: Serialize>(document: &T, writer: &mut W) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, document)?;
+    writeln!(writer)
+}
@@ -0,0 +1,563 @@
+use crate::account::{self, Account};
+use crate::balance::Balance;
+use crate::history::HistoryEntry;
+use crate::ledger::Ledger;
+use crate::report::{self, MoneyFlow, Report};
+use crate::settlement::Transfer;
+use crate::transaction::Transaction;
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const BODY_TOP: f32 = PAGE_HEIGHT - 95.0;
+const BODY_BOTTOM: f32 = 70.0;
+
+const FONT_SIZE: f32 = 10.0;
+const LINE_HEIGHT: f32 = 14.0;
+const HEADING_SIZE: f32 = 12.0;
+const TITLE_SIZE: f32 = 14.0;
+
+// Columns of the tables, amounts are right aligned at AMOUNT_RIGHT and the
+// changes of the history at CHANGE_RIGHT
+const DATE_LEFT: f32 = MARGIN;
+const NAME_LEFT: f32 = MARGIN + 65.0;
+const CHANGE_RIGHT: f32 = MARGIN + 225.0;
+const AMOUNT_RIGHT: f32 = MARGIN + 300.0;
+const NOTE_LEFT: f32 = MARGIN + 315.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+// Widths of the printable ASCII characters in Helvetica, in thousandths of
+// the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[derive(Copy, Clone)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+// Text placed on a page, `x` is the left edge or, for right aligned text,
+// the right edge
+struct Text {
+    x: f32,
+    y: f32,
+    font: Font,
+    size: f32,
+    right_aligned: bool,
+    text: String,
+}
+
+// Statement of one account, laid out on pages
+struct Statement {
+    title: String,
+    date: String,
+    pages: Vec<Vec<Text>>,
+}
+impl Statement {
+    fn new(title: String, date: String) -> Self {
+        Statement {
+            title,
+            date,
+            pages: vec![Vec::new()],
+        }
+    }
+    // Moves to a new page if the given height doesn't fit below `y`
+    fn reserve(&mut self, y: &mut f32, height: f32) {
+        if *y - height < BODY_BOTTOM {
+            self.pages.push(Vec::new());
+            *y = BODY_TOP;
+        }
+    }
+    fn push(&mut self, x: f32, y: f32, font: Font, size: f32, text: String) {
+        self.pages.last_mut().unwrap().push(Text {
+            x,
+            y,
+            font,
+            size,
+            right_aligned: false,
+            text,
+        });
+    }
+    fn push_right(&mut self, x: f32, y: f32, text: String) {
+        self.pages.last_mut().unwrap().push(Text {
+            x,
+            y,
+            font: Font::Regular,
+            size: FONT_SIZE,
+            right_aligned: true,
+            text,
+        });
+    }
+}
+
+/// Writes the PDF report of the account with the given acronym into the
+/// `output` folder. The account has to exist in the ledger.
+pub fn generate(
+    ledger: &Ledger,
+    balance: &Balance,
+    output: &Path,
+    acronym: &str,
+) -> io::Result<()> {
+    let account_index = account::find_index(acronym, ledger.accounts()).unwrap();
+    let title = format!(
+        "WG Abrechnung {} {}",
+        ledger.accounting_date(),
+        ledger.accounts()[account_index].name()
+    );
+    let mut buffer: Vec<u8> = Vec::new();
+    write(ledger, balance, &[account_index], &mut buffer)?;
+    fs::write(
+        output.join(title.replace(".", "_")).with_extension("pdf"),
+        buffer,
+    )
+}
+
+/// Writes one PDF with the reports of the accounts with the given acronyms
+/// into the `output` folder. The accounts have to exist in the ledger.
+pub fn generate_combined(
+    ledger: &Ledger,
+    balance: &Balance,
+    output: &Path,
+    acronyms: &[String],
+) -> io::Result<()> {
+    let title = format!("WG Abrechnung {}", ledger.accounting_date());
+    let account_indices: Vec<usize> = acronyms
+        .iter()
+        .map(|acronym| account::find_index(acronym, ledger.accounts()).unwrap())
+        .collect();
+    let mut buffer: Vec<u8> = Vec::new();
+    write(ledger, balance, &account_indices, &mut buffer)?;
+    fs::write(
+        output.join(title.replace(".", "_")).with_extension("pdf"),
+        buffer,
+    )
+}
+
+/// Writes a PDF with the reports of the accounts with the given indices.
+/// Each report starts on a new page and has its own page numbers.
+pub fn write<W: Write>(
+    ledger: &Ledger,
+    balance: &Balance,
+    account_indices: &[usize],
+    writer: &mut W,
+) -> io::Result<()> {
+    let statements: Vec<Statement> = account_indices
+        .iter()
+        .map(|&account_index| layout(ledger, balance, account_index))
+        .collect();
+    let title = if account_indices.len() == 1 {
+        format!(
+            "WG Abrechnung {} {}",
+            ledger.accounting_date(),
+            ledger.accounts()[account_indices[0]].name()
+        )
+    } else {
+        format!("WG Abrechnung {}", ledger.accounting_date())
+    };
+    writer.write_all(&render(&title, &statements))
+}
+
+fn layout(ledger: &Ledger, balance: &Balance, account_index: usize) -> Statement {
+    let accounts = ledger.accounts();
+    let currency = ledger.currency();
+    let report = Report::new(ledger, balance, account_index);
+    let mut statement = Statement::new(
+        format!("WG Abrechnung {}", accounts[account_index].name()),
+        format!("Stand {}", ledger.accounting_date()),
+    );
+
+    let mut y = BODY_TOP;
+    statement.push(
+        MARGIN,
+        y,
+        Font::Bold,
+        TITLE_SIZE,
+        format!(
+            "WG Abrechnung {} {}",
+            ledger.accounting_date(),
+            accounts[account_index].name()
+        ),
+    );
+    y -= LINE_HEIGHT;
+    add_transaction_table(
+        &mut statement,
+        &mut y,
+        "Zu zahlen",
+        MoneyFlow::Out,
+        accounts,
+        currency,
+        report.to_pay(),
+    );
+    add_transaction_table(
+        &mut statement,
+        &mut y,
+        "Zu gute",
+        MoneyFlow::In,
+        accounts,
+        currency,
+        report.to_receive(),
+    );
+    add_transaction_table(
+        &mut statement,
+        &mut y,
+        "Gezahlt",
+        MoneyFlow::Out,
+        accounts,
+        currency,
+        report.paid(),
+    );
+    add_transaction_table(
+        &mut statement,
+        &mut y,
+        "Erhalten",
+        MoneyFlow::In,
+        accounts,
+        currency,
+        report.received(),
+    );
+    add_debt_table(
+        &mut statement,
+        &mut y,
+        "Noch offen",
+        accounts,
+        currency,
+        report.open(),
+    );
+    add_debt_table(
+        &mut statement,
+        &mut y,
+        "Ausgleich",
+        accounts,
+        currency,
+        report.settlement(),
+    );
+    add_history_table(
+        &mut statement,
+        &mut y,
+        accounts,
+        currency,
+        account_index,
+        report.history(),
+    );
+    statement
+}
+
+fn add_heading(statement: &mut Statement, y: &mut f32, name: &str) {
+    // Keep the heading together with the first line of the table
+    *y -= LINE_HEIGHT;
+    statement.reserve(y, 2.0 * LINE_HEIGHT);
+    *y -= LINE_HEIGHT;
+    statement.push(MARGIN, *y, Font::Bold, HEADING_SIZE, name.to_owned());
+}
+
+fn add_transaction_table(
+    statement: &mut Statement,
+    y: &mut f32,
+    name: &str,
+    money_flow: MoneyFlow,
+    accounts: &[Account],
+    currency: &str,
+    transactions: &[&Transaction],
+) {
+    if transactions.is_empty() {
+        return;
+    }
+    let preposition = money_flow.preposition();
+    add_heading(statement, y, name);
+    for transaction in transactions {
+        statement.reserve(y, LINE_HEIGHT);
+        *y -= LINE_HEIGHT;
+        let other_index = money_flow.other_index(transaction);
+        let mut note = transaction.note();
+        if let Some(original_amount) = transaction.original_amount() {
+            note.push_str(&format!(
+                " ({})",
+                report::format_amount(original_amount.amount(), original_amount.currency())
+            ));
+        }
+        statement.push(
+            DATE_LEFT,
+            *y,
+            Font::Regular,
+            FONT_SIZE,
+            transaction.date().to_string(),
+        );
+        statement.push(
+            NAME_LEFT,
+            *y,
+            Font::Regular,
+            FONT_SIZE,
+            truncate(
+                &format!("{} {}", preposition, accounts[other_index].name()),
+                AMOUNT_RIGHT - NAME_LEFT - 70.0,
+            ),
+        );
+        statement.push_right(
+            AMOUNT_RIGHT,
+            *y,
+            report::format_amount(transaction.amount(), currency),
+        );
+        statement.push(
+            NOTE_LEFT,
+            *y,
+            Font::Regular,
+            FONT_SIZE,
+            truncate(&note, PAGE_WIDTH - MARGIN - NOTE_LEFT),
+        );
+    }
+}
+
+fn add_debt_table(
+    statement: &mut Statement,
+    y: &mut f32,
+    name: &str,
+    accounts: &[Account],
+    currency: &str,
+    transfers: &[Transfer],
+) {
+    add_heading(statement, y, name);
+    if transfers.is_empty() {
+        *y -= LINE_HEIGHT;
+        statement.push(MARGIN, *y, Font::Regular, FONT_SIZE, "-".to_owned());
+    }
+    for transfer in transfers {
+        statement.reserve(y, LINE_HEIGHT);
+        *y -= LINE_HEIGHT;
+        statement.push(
+            MARGIN,
+            *y,
+            Font::Regular,
+            FONT_SIZE,
+            truncate(
+                &format!(
+                    "{} -> {}",
+                    accounts[transfer.debtor_index()].name(),
+                    accounts[transfer.creditor_index()].name()
+                ),
+                AMOUNT_RIGHT - MARGIN - 70.0,
+            ),
+        );
+        statement.push_right(
+            AMOUNT_RIGHT,
+            *y,
+            report::format_amount(transfer.amount(), currency),
+        );
+    }
+}
+
+fn add_history_table(
+    statement: &mut Statement,
+    y: &mut f32,
+    accounts: &[Account],
+    currency: &str,
+    account_index: usize,
+    history: &[HistoryEntry],
+) {
+    if history.is_empty() {
+        return;
+    }
+    add_heading(statement, y, "Verlauf");
+    for entry in history {
+        statement.reserve(y, LINE_HEIGHT);
+        *y -= LINE_HEIGHT;
+        let transaction = entry.transaction();
+        let money_flow = MoneyFlow::of(transaction, account_index);
+        let preposition = money_flow.preposition();
+        let other_index = money_flow.other_index(transaction);
+        statement.push(
+            DATE_LEFT,
+            *y,
+            Font::Regular,
+            FONT_SIZE,
+            transaction.date().to_string(),
+        );
+        statement.push(
+            NAME_LEFT,
+            *y,
+            Font::Regular,
+            FONT_SIZE,
+            truncate(
+                &format!("{} {}", preposition, accounts[other_index].name()),
+                CHANGE_RIGHT - NAME_LEFT - 70.0,
+            ),
+        );
+        statement.push_right(
+            CHANGE_RIGHT,
+            *y,
+            report::format_amount(entry.change(), currency),
+        );
+        statement.push_right(
+            AMOUNT_RIGHT,
+            *y,
+            report::format_amount(entry.balance(), currency),
+        );
+        statement.push(
+            NOTE_LEFT,
+            *y,
+            Font::Regular,
+            FONT_SIZE,
+            truncate(&transaction.note(), PAGE_WIDTH - MARGIN - NOTE_LEFT),
+        );
+    }
+}
+
+fn render(title: &str, statements: &[Statement]) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+    let mut next_id = Ref::new(1);
+    let mut allocate = || next_id.bump();
+    let catalog_id = allocate();
+    let page_tree_id = allocate();
+    let info_id = allocate();
+    let regular_id = allocate();
+    let bold_id = allocate();
+
+    let mut page_ids: Vec<Ref> = Vec::new();
+    for statement in statements {
+        let page_count = statement.pages.len();
+        for (page_index, texts) in statement.pages.iter().enumerate() {
+            let page_id = allocate();
+            let content_id = allocate();
+            page_ids.push(page_id);
+
+            let mut content = Content::new();
+            // Header with the account and the accounting date on every page
+            show(
+                &mut content,
+                MARGIN,
+                PAGE_HEIGHT - 40.0,
+                Font::Regular,
+                9.0,
+                &statement.title,
+            );
+            let date_x = PAGE_WIDTH - MARGIN - text_width(&statement.date, 9.0);
+            show(
+                &mut content,
+                date_x,
+                PAGE_HEIGHT - 40.0,
+                Font::Regular,
+                9.0,
+                &statement.date,
+            );
+            content.set_line_width(0.5);
+            content.move_to(MARGIN, PAGE_HEIGHT - 46.0);
+            content.line_to(PAGE_WIDTH - MARGIN, PAGE_HEIGHT - 46.0);
+            content.stroke();
+            for text in texts {
+                let x = if text.right_aligned {
+                    text.x - text_width(&text.text, text.size)
+                } else {
+                    text.x
+                };
+                show(&mut content, x, text.y, text.font, text.size, &text.text);
+            }
+            let page_number = format!("Seite {} von {}", page_index + 1, page_count);
+            let page_number_x = (PAGE_WIDTH - text_width(&page_number, 9.0)) / 2.0;
+            show(
+                &mut content,
+                page_number_x,
+                35.0,
+                Font::Regular,
+                9.0,
+                &page_number,
+            );
+            pdf.stream(content_id, &content.finish());
+
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+            page.parent(page_tree_id);
+            page.contents(content_id);
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            fonts.pair(REGULAR, regular_id);
+            fonts.pair(BOLD, bold_id);
+            fonts.finish();
+            resources.finish();
+            page.finish();
+        }
+    }
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(page_ids.len() as i32);
+    pdf.document_info(info_id)
+        .title(TextStr(title))
+        .creator(TextStr("wg-accounting"));
+    // Base fonts every PDF reader has, so nothing needs to be embedded
+    pdf.type1_font(regular_id)
+        .base_font(Name(b"Helvetica"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.type1_font(bold_id)
+        .base_font(Name(b"Helvetica-Bold"))
+        .encoding_predefined(Name(b"WinAnsiEncoding"));
+    pdf.finish()
+}
+
+fn show(content: &mut Content, x: f32, y: f32, font: Font, size: f32, text: &str) {
+    let font_name = match font {
+        Font::Regular => REGULAR,
+        Font::Bold => BOLD,
+    };
+    content.begin_text();
+    content.set_font(font_name, size);
+    content.next_line(x, y);
+    content.show(Str(&encode(text)));
+    content.end_text();
+}
+
+// Encodes text in WinAnsiEncoding, which covers the Latin-1 characters and
+// the euro sign. Other characters are replaced by a question mark.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|character| match character {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => character as u8,
+            '€' => 0x80,
+            '…' => 0x85,
+            _ => b'?',
+        })
+        .collect()
+}
+
+// Approximate for characters other than printable ASCII and bold text
+fn text_width(text: &str, size: f32) -> f32 {
+    let thousandths: u32 = text
+        .chars()
+        .map(|character| match character {
+            ' '..='~' => HELVETICA_WIDTHS[character as usize - ' ' as usize] as u32,
+            '…' => 1000,
+            _ => 556,
+        })
+        .sum();
+    thousandths as f32 * size / 1000.0
+}
+
+// Shortens text to the given width with an ellipsis
+fn truncate(text: &str, width: f32) -> String {
+    if text_width(text, FONT_SIZE) <= width {
+        return text.to_owned();
+    }
+    let mut truncated = String::new();
+    for character in text.chars() {
+        truncated.push(character);
+        if text_width(&truncated, FONT_SIZE) + text_width("…", FONT_SIZE) > width {
+            truncated.pop();
+            break;
+        }
+    }
+    truncated.push('…');
+    truncated
+}
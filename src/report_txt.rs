@@ -1,141 +1,276 @@
 use crate::account::{self, Account};
-use crate::balance::{Balance, BalanceEntry};
+use crate::balance::Balance;
+use crate::history::HistoryEntry;
 use crate::ledger::Ledger;
+use crate::money::Money;
+use crate::period::Period;
+use crate::report::{self, MoneyFlow, PeriodReport, Report};
+use crate::settlement::Transfer;
 use crate::transaction::Transaction;
 
 use std::fmt::Write as FmtWrite;
 use std::fs::File;
-use std::io::Write as IoWrite;
+use std::io::{self, Write as IoWrite};
 use std::path::Path;
 
-enum MoneyFlow {
-    In,
-    Out,
-}
-
-pub fn generate(ledger: &Ledger, balance: &Balance, output: &Path, acronym: &String) {
+/// Writes the text report of the account with the given acronym into the
+/// `output` folder. The account has to exist in the ledger.
+pub fn generate(
+    ledger: &Ledger,
+    balance: &Balance,
+    output: &Path,
+    acronym: &str,
+) -> io::Result<()> {
     let accounts = ledger.accounts();
-    let account_index = account::find_index(acronym, accounts);
+    let currency = ledger.currency();
+    let account_index = account::find_index(acronym, accounts).unwrap();
     let account_name = accounts[account_index].name();
     let title = format!(
         "WG Abrechnung {} {}",
         ledger.accounting_date(),
         &account_name
     );
+    let report = Report::new(ledger, balance, account_index);
 
-    let mut incoming_invoices: Vec<&Transaction> = Vec::new();
-    let mut outgoing_invoices: Vec<&Transaction> = Vec::new();
-    for invoice in ledger.invoices() {
-        if invoice.sender_index() == account_index {
-            incoming_invoices.push(invoice);
-        } else if invoice.recipient_index() == account_index {
-            outgoing_invoices.push(invoice);
-        }
-    }
-
-    let mut outgoing_payments: Vec<&Transaction> = Vec::new();
-    let mut incoming_payments: Vec<&Transaction> = Vec::new();
-    for payment in ledger.payments() {
-        if payment.sender_index() == account_index {
-            outgoing_payments.push(payment);
-        } else if payment.recipient_index() == account_index {
-            incoming_payments.push(payment);
-        }
-    }
+    let mut file = File::create(output.join(title.replace(".", "_")).with_extension("txt"))?;
+    writeln!(&mut file, "{}", title)?;
+    add_transaction_table(
+        &mut file,
+        "Zu zahlen",
+        MoneyFlow::Out,
+        accounts,
+        currency,
+        report.to_pay(),
+    )?;
+    add_transaction_table(
+        &mut file,
+        "Zu gute",
+        MoneyFlow::In,
+        accounts,
+        currency,
+        report.to_receive(),
+    )?;
+    add_transaction_table(
+        &mut file,
+        "Gezahlt",
+        MoneyFlow::Out,
+        accounts,
+        currency,
+        report.paid(),
+    )?;
+    add_transaction_table(
+        &mut file,
+        "Erhalten",
+        MoneyFlow::In,
+        accounts,
+        currency,
+        report.received(),
+    )?;
+    add_debt_table(&mut file, "Noch offen", accounts, currency, report.open())?;
+    add_debt_table(
+        &mut file,
+        "Ausgleich",
+        accounts,
+        currency,
+        report.settlement(),
+    )?;
+    add_history_table(
+        &mut file,
+        accounts,
+        currency,
+        account_index,
+        report.history(),
+    )?;
+    Ok(())
+}
 
-    let mut relevant_balance_entries: Vec<&BalanceEntry> = Vec::new();
-    for balance_entry in balance.entries() {
-        if (balance_entry.sender_index() == account_index)
-            || (balance_entry.recipient_index() == account_index)
-        {
-            relevant_balance_entries.push(balance_entry);
-        }
-    }
+/// Writes the text statement of the account with the given acronym for the
+/// period into the `output` folder. The account has to exist in the ledger
+/// and the period has to end by the accounting date.
+pub fn generate_period(
+    ledger: &Ledger,
+    output: &Path,
+    acronym: &str,
+    period: Period,
+) -> io::Result<()> {
+    let accounts = ledger.accounts();
+    let currency = ledger.currency();
+    let account_index = account::find_index(acronym, accounts).unwrap();
+    let title = format!(
+        "WG Abrechnung {} {}",
+        period,
+        accounts[account_index].name()
+    );
+    let report = PeriodReport::new(ledger, account_index, period).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "period {} ends after the accounting date {}",
+                period,
+                ledger.accounting_date()
+            ),
+        )
+    })?;
 
-    let mut file =
-        File::create(output.join(&title.replace(".", "_")).with_extension("txt")).unwrap();
-    writeln!(&mut file, "{}", title).unwrap();
+    let mut file = File::create(output.join(title.replace(".", "_")).with_extension("txt"))?;
+    writeln!(&mut file, "{}", title)?;
+    let opening_name = match period.start().previous_day() {
+        Some(date) => format!("Offen am {}", date),
+        None => "Offen".to_owned(),
+    };
+    add_debt_table(
+        &mut file,
+        &opening_name,
+        accounts,
+        currency,
+        report.opening(),
+    )?;
     add_transaction_table(
         &mut file,
         "Zu zahlen",
         MoneyFlow::Out,
         accounts,
-        &mut incoming_invoices,
-    );
+        currency,
+        report.to_pay(),
+    )?;
     add_transaction_table(
         &mut file,
         "Zu gute",
         MoneyFlow::In,
         accounts,
-        &mut outgoing_invoices,
-    );
+        currency,
+        report.to_receive(),
+    )?;
     add_transaction_table(
         &mut file,
         "Gezahlt",
         MoneyFlow::Out,
         accounts,
-        &mut outgoing_payments,
-    );
+        currency,
+        report.paid(),
+    )?;
     add_transaction_table(
         &mut file,
         "Erhalten",
         MoneyFlow::In,
         accounts,
-        &mut incoming_payments,
-    );
-    writeln!(file, "\nNoch offen:").unwrap();
-    let mut uncleared_balance_found = false;
-    relevant_balance_entries.sort_by(|a, b| a.balance().partial_cmp(&b.balance()).unwrap());
-    for balance_entry in relevant_balance_entries {
-        let balance = (balance_entry.balance() * 100.0).round() / 100.0;
-        if balance.abs() >= 0.01 {
-            uncleared_balance_found = true;
-            let debtor_creditor = if balance > 0.0 {
-                (
-                    balance_entry.recipient_index(),
-                    balance_entry.sender_index(),
-                )
-            } else {
-                (
-                    balance_entry.sender_index(),
-                    balance_entry.recipient_index(),
-                )
-            };
-            let mut line = String::new();
-            write!(
-                line,
-                "{} -> {}",
-                accounts[debtor_creditor.0].name(),
-                accounts[debtor_creditor.1].name()
-            )
-            .unwrap();
-            for _ in line.chars().count()..35 {
-                line.push(' ');
-            }
-            add_amount_to_string(balance.abs(), &mut line);
-            // Write line to file
-            writeln!(file, "{}", line).unwrap();
+        currency,
+        report.received(),
+    )?;
+    add_debt_table(
+        &mut file,
+        &format!("Offen am {}", period.end()),
+        accounts,
+        currency,
+        report.closing(),
+    )?;
+    Ok(())
+}
+
+fn add_history_table(
+    file: &mut File,
+    accounts: &[Account],
+    currency: &str,
+    account_index: usize,
+    history: &[HistoryEntry],
+) -> io::Result<()> {
+    if history.is_empty() {
+        return Ok(());
+    }
+    writeln!(file, "\nVerlauf:")?;
+    for entry in history {
+        let transaction = entry.transaction();
+        let money_flow = MoneyFlow::of(transaction, account_index);
+        let preposition = money_flow.preposition();
+        let other_index = money_flow.other_index(transaction);
+        let mut line = String::new();
+        write!(line, "{}", transaction.date()).unwrap();
+        for _ in line.chars().count()..(15 - preposition.len()) {
+            line.push(' ');
+        }
+        write!(line, "{} {}", preposition, accounts[other_index].name()).unwrap();
+        for _ in line.chars().count()..35 {
+            line.push(' ');
+        }
+        line.push(if entry.change().is_negative() {
+            '-'
+        } else {
+            '+'
+        });
+        line.push_str(&report::format_amount(entry.change().abs(), currency));
+        for _ in line.chars().count()..50 {
+            line.push(' ');
         }
+        line.push_str(&report::format_amount(entry.balance(), currency));
+        for _ in line.chars().count()..65 {
+            line.push(' ');
+        }
+        write!(line, "{}", transaction.note()).unwrap();
+        // Write line to file
+        writeln!(file, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn add_debt_table(
+    file: &mut File,
+    name: &str,
+    accounts: &[Account],
+    currency: &str,
+    transfers: &[Transfer],
+) -> io::Result<()> {
+    writeln!(file, "\n{}:", name)?;
+    if transfers.is_empty() {
+        writeln!(file, "-")?;
     }
-    if !uncleared_balance_found {
-        writeln!(file, "-").unwrap();
+    for transfer in transfers {
+        add_debt_line(
+            file,
+            accounts,
+            currency,
+            transfer.debtor_index(),
+            transfer.creditor_index(),
+            transfer.amount(),
+        )?;
     }
+    Ok(())
+}
+
+fn add_debt_line(
+    file: &mut File,
+    accounts: &[Account],
+    currency: &str,
+    debtor_index: usize,
+    creditor_index: usize,
+    amount: Money,
+) -> io::Result<()> {
+    let mut line = String::new();
+    write!(
+        line,
+        "{} -> {}",
+        accounts[debtor_index].name(),
+        accounts[creditor_index].name()
+    )
+    .unwrap();
+    for _ in line.chars().count()..35 {
+        line.push(' ');
+    }
+    line.push_str(&report::format_amount(amount, currency));
+    // Write line to file
+    writeln!(file, "{}", line)
 }
 
 fn add_transaction_table(
     file: &mut File,
     name: &str,
     money_flow: MoneyFlow,
-    accounts: &Vec<Account>,
-    transactions: &mut Vec<&Transaction>,
-) {
-    if transactions.len() != 0 {
-        transactions.sort();
-        let preposition = match money_flow {
-            MoneyFlow::In => "Von",
-            MoneyFlow::Out => "An",
-        };
-        writeln!(file, "\n{}:", name).unwrap();
+    accounts: &[Account],
+    currency: &str,
+    transactions: &[&Transaction],
+) -> io::Result<()> {
+    if !transactions.is_empty() {
+        let preposition = money_flow.preposition();
+        writeln!(file, "\n{}:", name)?;
         for transaction in transactions {
             let mut line = String::new();
             write!(line, "{}", transaction.date()).unwrap();
@@ -144,32 +279,27 @@ fn add_transaction_table(
             for _ in line.chars().count()..(15 - preposition.len()) {
                 line.push(' ');
             }
-            let other_index = match money_flow {
-                MoneyFlow::In => transaction.sender_index(),
-                MoneyFlow::Out => transaction.recipient_index(),
-            };
+            let other_index = money_flow.other_index(transaction);
             write!(line, "{} {}", preposition, accounts[other_index].name()).unwrap();
             for _ in line.chars().count()..35 {
                 line.push(' ');
             }
-            add_amount_to_string(transaction.amount(), &mut line);
+            line.push_str(&report::format_amount(transaction.amount(), currency));
             for _ in line.chars().count()..50 {
                 line.push(' ');
             }
             write!(line, "{}", transaction.note()).unwrap();
+            if let Some(original_amount) = transaction.original_amount() {
+                write!(
+                    line,
+                    " ({})",
+                    report::format_amount(original_amount.amount(), original_amount.currency())
+                )
+                .unwrap();
+            }
             // Write line to file
-            writeln!(file, "{}", line).unwrap();
+            writeln!(file, "{}", line)?;
         }
     }
-}
-
-fn add_amount_to_string(amount: f64, string: &mut String) {
-    assert!(amount >= 0.0);
-    write!(string, "{}.", amount.trunc()).unwrap();
-    let cents = (amount.fract() * 100.0).round();
-    if cents > 0.0 {
-        write!(string, "{} CHF", cents).unwrap();
-    } else {
-        write!(string, "- CHF").unwrap();
-    }
+    Ok(())
 }
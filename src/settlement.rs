@@ -0,0 +1,247 @@
+use crate::account::Account;
+use crate::balance::Balance;
+use crate::money::Money;
+
+// Above this number of accounts with an open position the exact search gets
+// too slow and the transfers are determined greedily
+const EXACT_SEARCH_LIMIT: usize = 12;
+
+/// Payment from a debtor to a creditor which settles open balances.
+pub struct Transfer {
+    debtor_index: usize,
+    creditor_index: usize,
+    amount: Money,
+}
+impl Transfer {
+    pub(crate) fn new(debtor_index: usize, creditor_index: usize, amount: Money) -> Self {
+        Transfer {
+            debtor_index,
+            creditor_index,
+            amount,
+        }
+    }
+    pub fn debtor_index(&self) -> usize {
+        self.debtor_index
+    }
+    pub fn creditor_index(&self) -> usize {
+        self.creditor_index
+    }
+    pub fn amount(&self) -> Money {
+        self.amount
+    }
+    pub fn to_string(&self, accounts: &[Account], currency: &str) -> String {
+        format!(
+            "{} -> {} {} {}",
+            accounts[self.debtor_index].acronym(),
+            accounts[self.creditor_index].acronym(),
+            self.amount,
+            currency
+        )
+    }
+}
+
+/// Net position per account, positive if the account gets money back.
+pub fn net_positions(balance: &Balance, account_count: usize) -> Vec<Money> {
+    let mut positions = vec![Money::ZERO; account_count];
+    for entry in balance.entries() {
+        positions[entry.sender_index()] += entry.balance();
+        positions[entry.recipient_index()] -= entry.balance();
+    }
+    positions
+}
+
+/// Transfers which settle all open balances. The number of transfers is
+/// minimal for up to 12 accounts with an open position and near-minimal above.
+pub fn settle(balance: &Balance, account_count: usize) -> Vec<Transfer> {
+    let positions = net_positions(balance, account_count);
+    let open_indices: Vec<usize> = (0..account_count)
+        .filter(|&index| !positions[index].is_zero())
+        .collect();
+
+    // Every group of accounts whose positions sum up to zero can be settled
+    // on its own with one transfer less than it has accounts. The more groups,
+    // the fewer transfers.
+    let groups = if open_indices.len() <= EXACT_SEARCH_LIMIT {
+        zero_sum_groups(&open_indices, &positions)
+    } else {
+        vec![open_indices]
+    };
+
+    let mut transfers: Vec<Transfer> = Vec::new();
+    for group in groups {
+        settle_greedily(&group, &positions, &mut transfers);
+    }
+    transfers
+}
+
+fn zero_sum_groups(indices: &[usize], positions: &[Money]) -> Vec<Vec<usize>> {
+    let count = indices.len();
+    let full_mask = (1usize << count) - 1;
+    let sums: Vec<Money> = (0..=full_mask)
+        .map(|mask| {
+            (0..count)
+                .filter(|bit| mask & (1 << bit) != 0)
+                .map(|bit| positions[indices[bit]])
+                .sum()
+        })
+        .collect();
+
+    // Maximum number of zero sum groups per zero sum mask and the group
+    // containing the lowest set bit of that mask
+    let mut group_counts: Vec<usize> = vec![0; full_mask + 1];
+    let mut first_groups: Vec<usize> = vec![0; full_mask + 1];
+    for mask in 1..=full_mask {
+        if !sums[mask].is_zero() {
+            continue;
+        }
+        let lowest_bit = mask & mask.wrapping_neg();
+        let rest = mask ^ lowest_bit;
+        let mut sub_rest = rest;
+        loop {
+            let group = sub_rest | lowest_bit;
+            let remainder = mask ^ group;
+            if sums[group].is_zero() {
+                let group_count = 1 + group_counts[remainder];
+                if group_count > group_counts[mask] {
+                    group_counts[mask] = group_count;
+                    first_groups[mask] = group;
+                }
+            }
+            if sub_rest == 0 {
+                break;
+            }
+            sub_rest = (sub_rest - 1) & rest;
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut mask = full_mask;
+    while mask != 0 {
+        let group = first_groups[mask];
+        groups.push(
+            (0..count)
+                .filter(|bit| group & (1 << bit) != 0)
+                .map(|bit| indices[bit])
+                .collect(),
+        );
+        mask ^= group;
+    }
+    groups
+}
+
+fn settle_greedily(group: &[usize], positions: &[Money], transfers: &mut Vec<Transfer>) {
+    let mut remaining: Vec<(usize, Money)> = group
+        .iter()
+        .map(|&index| (index, positions[index]))
+        .collect();
+    loop {
+        // Largest debtor pays the largest creditor, ties by account order
+        let debtor = remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, position))| position.is_negative())
+            .min_by_key(|(_, (index, position))| (*position, *index))
+            .map(|(slot, _)| slot);
+        let creditor = remaining
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, position))| *position > Money::ZERO)
+            .max_by_key(|(_, (index, position))| (*position, usize::MAX - *index))
+            .map(|(slot, _)| slot);
+        let (debtor, creditor) = match (debtor, creditor) {
+            (Some(debtor), Some(creditor)) => (debtor, creditor),
+            _ => break,
+        };
+        let amount = (-remaining[debtor].1).min(remaining[creditor].1);
+        remaining[debtor].1 += amount;
+        remaining[creditor].1 -= amount;
+        transfers.push(Transfer {
+            debtor_index: remaining[debtor].0,
+            creditor_index: remaining[creditor].0,
+            amount,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date::Date;
+    use crate::transaction::Transaction;
+
+    // Balance with the net positions in whole units, made of payments along a
+    // chain of accounts
+    fn balance(positions: &[i64]) -> Balance {
+        let date = Date::from_ymd(2024, 1, 1).unwrap();
+        let mut balance = Balance::new();
+        let mut carried = 0;
+        for (index, position) in positions[..positions.len() - 1].iter().enumerate() {
+            carried += position;
+            let payment = if carried > 0 {
+                Transaction::new(
+                    index,
+                    index + 1,
+                    Money::from_cents(carried * 100),
+                    date,
+                    "".to_owned(),
+                )
+            } else {
+                Transaction::new(
+                    index + 1,
+                    index,
+                    Money::from_cents(-carried * 100),
+                    date,
+                    "".to_owned(),
+                )
+            };
+            balance.add_payment(&payment);
+        }
+        balance
+    }
+
+    // Number of transfers, after checking that they settle all positions
+    fn settled_transfer_count(positions: &[i64]) -> usize {
+        let balance = balance(positions);
+        let mut remaining = net_positions(&balance, positions.len());
+        assert_eq!(
+            remaining,
+            positions
+                .iter()
+                .map(|&position| Money::from_cents(position * 100))
+                .collect::<Vec<Money>>()
+        );
+        let transfers = settle(&balance, positions.len());
+        for transfer in &transfers {
+            assert!(transfer.amount() > Money::ZERO);
+            remaining[transfer.debtor_index()] += transfer.amount();
+            remaining[transfer.creditor_index()] -= transfer.amount();
+        }
+        assert!(remaining.iter().all(|position| position.is_zero()));
+        transfers.len()
+    }
+
+    #[test]
+    fn settle_pairs_separately() {
+        assert_eq!(settled_transfer_count(&[5, 5, -5, -5]), 2);
+        assert_eq!(settled_transfer_count(&[5, -5, 5, -5]), 2);
+    }
+
+    #[test]
+    fn settle_zero_sum_subgroup() {
+        // Greedily -7 would pay 5 first and leave four transfers, while
+        // {-5, 5} and {-7, 3, 4} need three
+        assert_eq!(settled_transfer_count(&[-7, -5, 3, 4, 5]), 3);
+        assert_eq!(settled_transfer_count(&[0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn settle_greedily_above_exact_search_limit() {
+        // Twelve open accounts in five zero sum groups are settled exactly
+        let positions = [-7, -5, 3, 4, 5, 10, -10, 20, -20, 30, -15, -15];
+        assert_eq!(positions.len(), EXACT_SEARCH_LIMIT);
+        assert_eq!(settled_transfer_count(&positions), 7);
+        // Thirteen in six groups are settled greedily with one transfer more
+        let positions = [-7, -5, 3, 4, 5, 10, -10, 20, -20, 30, -30, 40, -40];
+        assert_eq!(settled_transfer_count(&positions), 8);
+    }
+}
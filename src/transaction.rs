@@ -1,21 +1,27 @@
 use crate::account::Account;
+use crate::currency::ForeignAmount;
 use crate::date::Date;
+use crate::money::Money;
 
 use std::cmp::Ordering;
 
+/// Invoice or payment from a sender to a recipient account.
 #[derive(Clone)]
 pub struct Transaction {
     sender_index: usize,
     recipient_index: usize,
-    amount: f64,
+    amount: Money,
     date: Date,
     note: String,
+    category: Option<String>,
+    tags: Vec<String>,
+    original_amount: Option<ForeignAmount>,
 }
 impl Transaction {
     pub fn new(
         sender_index_param: usize,
         recipient_index_param: usize,
-        amount_param: f64,
+        amount_param: Money,
         date_param: Date,
         note_param: String,
     ) -> Self {
@@ -25,16 +31,25 @@ impl Transaction {
             recipient_index: recipient_index_param,
             amount: amount_param,
             date: date_param,
+            category: parse_category(&note_param),
+            tags: parse_tags(&note_param),
             note: note_param,
+            original_amount: None,
         }
     }
+    /// Keeps the amount as written in the ledger if it was converted from
+    /// another currency.
+    pub fn with_original_amount(mut self, original_amount: Option<ForeignAmount>) -> Self {
+        self.original_amount = original_amount;
+        self
+    }
     pub fn sender_index(&self) -> usize {
         self.sender_index
     }
     pub fn recipient_index(&self) -> usize {
         self.recipient_index
     }
-    pub fn amount(&self) -> f64 {
+    pub fn amount(&self) -> Money {
         self.amount
     }
     pub fn date(&self) -> Date {
@@ -43,12 +58,32 @@ impl Transaction {
     pub fn note(&self) -> String {
         self.note.clone()
     }
-    pub fn to_string(&self, accounts: &Vec<Account>) -> String {
+    /// Category written as `[category]` in the note.
+    pub fn category(&self) -> Option<&String> {
+        self.category.as_ref()
+    }
+    /// Tags written as `#tag` in the note, without the `#`.
+    pub fn tags(&self) -> &Vec<String> {
+        &self.tags
+    }
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own_tag| own_tag == tag)
+    }
+    pub fn original_amount(&self) -> Option<&ForeignAmount> {
+        self.original_amount.as_ref()
+    }
+    pub fn to_string(&self, accounts: &[Account], currency: &str) -> String {
+        let original_amount = match &self.original_amount {
+            Some(original_amount) => format!(" ({})", original_amount),
+            None => String::new(),
+        };
         format!(
-            "{} -> {} {} CHF\t{}\t{}",
+            "{} -> {} {} {}{}\t{}\t{}",
             accounts[self.sender_index].acronym(),
             accounts[self.recipient_index].acronym(),
             self.amount,
+            currency,
+            original_amount,
             self.date,
             self.note
         )
@@ -73,3 +108,33 @@ impl PartialEq for Transaction {
             && (self.note == other.note)
     }
 }
+
+/// First `[category]` in a note.
+pub(crate) fn parse_category(note: &str) -> Option<String> {
+    let start = note.find('[')? + 1;
+    let length = note[start..].find(']')?;
+    let category = note[start..start + length].trim();
+    if category.is_empty() {
+        None
+    } else {
+        Some(category.to_owned())
+    }
+}
+
+/// All `#tag` words in a note. A tag consists of letters, digits, `-` and
+/// `_`.
+pub(crate) fn parse_tags(note: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in note.split_whitespace() {
+        if let Some(tag) = word.strip_prefix('#') {
+            let tag: String = tag
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+                .collect();
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}